@@ -3,12 +3,35 @@ use std::{
     io::{BufRead, BufReader},
     path::PathBuf,
     process::{Command, ExitStatus, Stdio},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::{Context, Result};
 
 const CARGO_REGISTRY_TOKEN: &str = "CARGO_REGISTRY_TOKEN";
 
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(10);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(600);
+
+/// Lowercased stderr fragments of failures that are worth another attempt:
+/// rate limits, server errors and network trouble.
+const RETRYABLE_ERRORS: &[&str] = &[
+    "(status 429",
+    "too many requests",
+    "try again after",
+    "(status 500",
+    "(status 502",
+    "(status 503",
+    "(status 504",
+    "spurious network error",
+    "timed out",
+    "connection reset",
+    "connection refused",
+    "could not resolve host",
+    "failed to send request",
+];
+
 pub struct CmdOutput {
     pub status: ExitStatus,
     // pub stdout: String,
@@ -19,13 +42,69 @@ fn exit_code(status: ExitStatus) -> String {
     status.code().map_or_else(|| "?".to_string(), |c| c.to_string())
 }
 
+/// How a failed `cargo publish` should be handled.
+#[derive(Debug, PartialEq, Eq)]
+enum Failure {
+    /// Transient registry or network trouble, optionally with the time the registry asked us
+    /// to wait until.
+    Retryable(Option<SystemTime>),
+    Fatal,
+}
+
+impl Failure {
+    fn classify(stderr: &str) -> Self {
+        let stderr = stderr.to_lowercase();
+        if !RETRYABLE_ERRORS.iter().any(|e| stderr.contains(e)) {
+            return Self::Fatal;
+        }
+        let retry_after = stderr
+            .split_once("try again after ")
+            .and_then(|(_, rest)| rest.split_once(" gmt"))
+            .and_then(|(date, _)| parse_http_date(date));
+        Self::Retryable(retry_after)
+    }
+}
+
+/// Parse the date in crates.io's "try again after Tue, 19 Nov 2024 14:00:00 GMT" message.
+/// Expects the lowercased date without the trailing `GMT`.
+fn parse_http_date(date: &str) -> Option<SystemTime> {
+    const MONTHS: [&str; 12] =
+        ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+    let mut parts = date.split_whitespace().skip(1);
+    let day: u64 = parts.next()?.parse().ok()?;
+    let month = parts.next()?;
+    let month = MONTHS.iter().position(|m| *m == month)? as u64 + 1;
+    let year: u64 = parts.next()?.parse().ok()?;
+    let mut time = parts.next()?.split(':').map(|s| s.parse::<u64>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+    // Days since the unix epoch for a date in the proleptic Gregorian calendar.
+    let (y, m) = if month <= 2 { (year - 1, month + 9) } else { (year, month - 3) };
+    let era = y / 400;
+    let yoe = y - era * 400;
+    let doy = (153 * m + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = (era * 146_097 + doe).checked_sub(719_468)?;
+    let secs = days * 86_400 + hour * 3_600 + minute * 60 + second;
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// Exponential backoff for the given attempt, or until the time the registry asked for.
+fn retry_delay(attempt: u32, retry_after: Option<SystemTime>) -> Duration {
+    if let Some(retry_after) = retry_after {
+        let wait = retry_after.duration_since(SystemTime::now()).unwrap_or_default();
+        return wait + Duration::from_secs(1);
+    }
+    RETRY_BASE_DELAY.saturating_mul(2u32.saturating_pow(attempt - 1)).min(RETRY_MAX_DELAY)
+}
+
 pub struct CargoCommand {
     current_dir: PathBuf,
+    max_attempts: u32,
 }
 
 impl CargoCommand {
-    pub const fn new(current_dir: PathBuf) -> Self {
-        Self { current_dir }
+    pub const fn new(current_dir: PathBuf, max_attempts: u32) -> Self {
+        Self { current_dir, max_attempts }
     }
 
     pub fn check(&self, package_name: &str) -> Result<()> {
@@ -41,22 +120,45 @@ impl CargoCommand {
         Ok(())
     }
 
-    pub fn publish(&self, package_name: &str, dry_run: bool) -> Result<()> {
+    /// Run `cargo publish`, retrying transient failures with exponential backoff.
+    pub fn publish(&self, package_name: &str, registry: Option<&str>, dry_run: bool) -> Result<()> {
         let mut args = vec!["publish", "-p", package_name];
+        if let Some(registry) = registry {
+            args.extend(["--registry", registry]);
+        }
         if dry_run {
             args.push("--dry-run");
         }
-        let output = self.run(&args)?;
-        if !output.status.success() {
-            anyhow::bail!(
-                "`cargo publish -p {}{}` failed with exit code {}:\n{}",
-                package_name,
-                if dry_run { " --dry-run" } else { "" },
-                exit_code(output.status),
-                output.stderr,
-            );
+        let command = format!("cargo {}", args.join(" "));
+        let mut attempt = 1;
+        loop {
+            let output = self.run(&args)?;
+            if output.status.success() {
+                return Ok(());
+            }
+            // An earlier attempt may have been uploaded even though its response got lost.
+            if attempt > 1 && output.stderr.contains("already exists") {
+                eprintln!("  · {package_name} was uploaded by a previous attempt");
+                return Ok(());
+            }
+            match Failure::classify(&output.stderr) {
+                Failure::Retryable(retry_after) if attempt < self.max_attempts => {
+                    let delay = retry_delay(attempt, retry_after);
+                    eprintln!(
+                        "  ! `{command}` failed (attempt {attempt}/{}), retrying in {}s",
+                        self.max_attempts,
+                        delay.as_secs(),
+                    );
+                    thread::sleep(delay);
+                    attempt += 1;
+                }
+                _ => anyhow::bail!(
+                    "`{command}` failed with exit code {} after {attempt} attempt(s):\n{}",
+                    exit_code(output.status),
+                    output.stderr,
+                ),
+            }
         }
-        Ok(())
     }
 
     pub fn run(&self, args: &[&str]) -> Result<CmdOutput> {
@@ -100,3 +202,44 @@ impl CargoCommand {
         Ok(CmdOutput { status: output.status, stderr: output_stderr })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{Failure, parse_http_date};
+
+    #[test]
+    fn parses_crates_io_retry_after() {
+        let stderr = "error: failed to publish to registry at https://crates.io\n\n\
+            Caused by:\n  the remote server responded with an error (status 429 Too Many \
+            Requests): You have published too many new crates in a short period of time. \
+            Please try again after Tue, 19 Nov 2024 14:00:00 GMT or email help@crates.io \
+            to have your limit increased.";
+        // 2024-11-19T14:00:00Z
+        let retry_after = UNIX_EPOCH + Duration::from_hours(481_118);
+        assert_eq!(Failure::classify(stderr), Failure::Retryable(Some(retry_after)));
+        assert_eq!(parse_http_date("tue, 19 nov 2024 14:00:00"), Some(retry_after));
+    }
+
+    #[test]
+    fn parses_leap_day() {
+        // 2024-02-29T00:00:05Z
+        let expected = UNIX_EPOCH + Duration::from_secs(1_709_164_805);
+        assert_eq!(parse_http_date("thu, 29 feb 2024 00:00:05"), Some(expected));
+        assert_eq!(parse_http_date("thu, 29 foo 2024 00:00:05"), None);
+    }
+
+    #[test]
+    fn classifies_failures() {
+        let stderr = "error: failed to publish to registry at https://crates.io\n\n\
+            Caused by:\n  the remote server responded with an error (status 503 Service \
+            Unavailable): Service Unavailable";
+        assert_eq!(Failure::classify(stderr), Failure::Retryable(None));
+
+        let stderr = "error: failed to publish to registry at https://crates.io\n\n\
+            Caused by:\n  the remote server responded with an error (status 400 Bad Request): \
+            crate version `0.1.0` is already uploaded";
+        assert_eq!(Failure::classify(stderr), Failure::Fatal);
+    }
+}
@@ -0,0 +1,86 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use similar::TextDiff;
+
+#[derive(Debug)]
+struct FileChange {
+    /// Content on disk, `None` for a file that does not exist yet.
+    original: Option<String>,
+    content: String,
+}
+
+/// File edits collected in memory, so they can be written together
+/// or printed as a diff by `update --dry-run`.
+#[derive(Debug, Default)]
+pub struct Changes {
+    files: BTreeMap<PathBuf, FileChange>,
+}
+
+impl Changes {
+    /// Content of `path` including the edits made so far.
+    pub fn read(&self, path: &Path) -> Result<String> {
+        if let Some(change) = self.files.get(path) {
+            return Ok(change.content.clone());
+        }
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))
+    }
+
+    /// Like [`Changes::read`], but a missing file reads as empty.
+    pub fn read_or_default(&self, path: &Path) -> String {
+        self.read(path).unwrap_or_default()
+    }
+
+    pub fn write(&mut self, path: &Path, content: String) {
+        if let Some(change) = self.files.get_mut(path) {
+            change.content = content;
+            return;
+        }
+        let original = fs::read_to_string(path).ok();
+        self.files.insert(path.to_path_buf(), FileChange { original, content });
+    }
+
+    /// Files whose content differs from what is on disk.
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.files
+            .iter()
+            .filter(|(_, change)| change.original.as_deref() != Some(change.content.as_str()))
+            .map(|(path, _)| path.as_path())
+    }
+
+    /// Unified diff of all changes, with paths relative to `root`.
+    pub fn diff(&self, root: &Path) -> String {
+        let mut out = String::new();
+        for (path, change) in &self.files {
+            let original = change.original.as_deref().unwrap_or_default();
+            if original == change.content && change.original.is_some() {
+                continue;
+            }
+            let name = path.strip_prefix(root).unwrap_or(path).display().to_string();
+            let old = if change.original.is_some() {
+                format!("a/{name}")
+            } else {
+                "/dev/null".to_string()
+            };
+            let new = format!("b/{name}");
+            let diff = TextDiff::from_lines(original, change.content.as_str());
+            out.push_str(&diff.unified_diff().context_radius(3).header(&old, &new).to_string());
+        }
+        out
+    }
+
+    pub fn apply(self) -> Result<()> {
+        for (path, change) in self.files {
+            if change.original.as_deref() == Some(change.content.as_str()) {
+                continue;
+            }
+            fs::write(&path, change.content)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
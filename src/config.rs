@@ -1,15 +1,26 @@
 use std::{
+    collections::BTreeMap,
     fs,
     path::{Path, PathBuf},
 };
 
 use anyhow::{Context, Result};
+use cargo_metadata::MetadataCommand;
+use regex::Regex;
 use serde::Deserialize;
 
-use crate::versioning::{cargo::CargoToml, package_json::PackageJson};
+use crate::{
+    changes::Changes,
+    publish_report::PackageKind,
+    versioning::{cargo::CargoToml, package_json::PackageJson},
+};
 
 const RELEASE_CONFIG: &str = "oxc_release.toml";
 
+const DEFAULT_TAG_TEMPLATE: &str = "{name}_v{version}";
+
+const DEFAULT_COMMIT_MESSAGE: &str = "release({name}): v{version}";
+
 #[derive(Debug, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct ReleaseConfig {
@@ -23,8 +34,10 @@ impl ReleaseConfig {
             fs::read_to_string(cwd.join(RELEASE_CONFIG)).context("failed to read release.toml")?;
         let mut config: Self = toml::from_str(&s).context("failed to parse release.toml")?;
         for release_set in &mut config.release_sets {
+            let add_workspace_dependencies = release_set.add_workspace_dependencies;
             for versioned_file in &mut release_set.versioned_files {
-                versioned_file.content = VersionedContent::read(&cwd.join(&versioned_file.path))?;
+                let path = cwd.join(&versioned_file.path);
+                versioned_file.content = VersionedContent::read(&path, add_workspace_dependencies)?;
             }
         }
         Ok(config)
@@ -40,6 +53,7 @@ impl ReleaseConfig {
     }
 }
 
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Debug, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct ReleaseSet {
@@ -49,6 +63,56 @@ pub struct ReleaseSet {
 
     pub scopes_for_breaking_change: Option<Vec<String>>,
 
+    /// Version to bump from when no release tag exists yet.
+    /// Defaults to the version of `root_crate`.
+    pub initial_version: Option<String>,
+
+    /// Name of the cargo registry to publish to, as configured in `[registries]`.
+    /// Defaults to crates.io.
+    pub registry: Option<String>,
+
+    /// Base URL of the sparse index used to check for already published crates,
+    /// e.g. `file:///path/to/index` in tests. Defaults to the index of `registry`.
+    pub index_url: Option<String>,
+
+    /// Git tag template with `{name}` and `{version}` placeholders, e.g. `{name}@{version}`
+    /// or `v{version}`. Defaults to `{name}_v{version}`.
+    pub tag_template: Option<String>,
+
+    /// Message template for `update --commit`, with `{name}` and `{version}` placeholders.
+    /// Defaults to `release({name}): v{version}`.
+    pub commit_message: Option<String>,
+
+    /// Version each package on its own from the commits touching its directory, with
+    /// per-package tags rendered from `tag_template` with `{name}` set to the package name.
+    #[serde(default)]
+    pub independent: bool,
+
+    /// Keep the version of packages without commits since the last release,
+    /// unless they depend on a bumped package or are the `root_crate`, whose version names
+    /// the release. Always the case when `independent`.
+    #[serde(default)]
+    pub skip_unchanged: bool,
+
+    /// Raise the detected bump to what `cargo semver-checks` requires for the public API
+    /// changes since the previous release tag.
+    #[serde(default)]
+    pub semver_checks: bool,
+
+    /// Add released crates missing from `[workspace.dependencies]` instead of warning about them.
+    #[serde(default)]
+    pub add_workspace_dependencies: bool,
+
+    /// Directory for the `{NAME}_VERSION` and `{NAME}_CHANGELOG` files and the publish
+    /// journal, relative to the workspace. Defaults to cargo's target directory.
+    pub output_dir: Option<PathBuf>,
+
+    /// npm-compatible client used to publish `package.json` files, defaults to `npm`.
+    pub npm_client: Option<String>,
+
+    /// npm registry URL, defaults to the client's configured registry.
+    pub npm_registry: Option<String>,
+
     versioned_files: Vec<VersionedFile>,
 }
 
@@ -65,18 +129,159 @@ impl ReleaseSet {
         packages
     }
 
-    pub fn update_version(&self, version: &str) -> Result<()> {
+    pub fn versioned_file_paths(&self) -> impl Iterator<Item = &Path> {
+        self.versioned_files.iter().map(|v| v.path.as_path())
+    }
+
+    /// Non-private `package.json` files to publish to npm.
+    #[must_use]
+    pub fn npm_packages(&self) -> Vec<&PackageJson> {
+        self.versioned_files
+            .iter()
+            .filter_map(|v| match &v.content {
+                VersionedContent::PackageJson(package_json) if !package_json.is_private() => {
+                    Some(package_json)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn update_version(&self, version: &str, changes: &mut Changes) -> Result<()> {
+        let versions = self
+            .versioned_packages()
+            .into_iter()
+            .map(|package| (package.id(), version.to_string()))
+            .collect::<BTreeMap<_, _>>();
+        self.update_versions(&versions, changes)
+    }
+
+    /// Bump each package in `versions` leaving the others untouched.
+    pub fn update_versions(
+        &self,
+        versions: &BTreeMap<PackageId, String>,
+        changes: &mut Changes,
+    ) -> Result<()> {
         for versioned_file in &self.versioned_files {
-            versioned_file.content.update_version(version)?;
+            versioned_file.content.update_versions(versions, changes)?;
         }
         Ok(())
     }
 
     pub fn commits_range(&self, version: &str) -> String {
-        format!("{}_v{version}..HEAD", self.name)
+        format!("{}..HEAD", self.tag_name(version))
+    }
+
+    /// Like [`ReleaseSet::commits_range`], for the tag of a single package.
+    pub fn package_commits_range(&self, package: &str, version: &str) -> String {
+        format!("{}..HEAD", self.package_tag_name(package, version))
+    }
+
+    /// The git tag of a released `version`.
+    pub fn tag_name(&self, version: &str) -> String {
+        render_template(self.tag_template(), &self.name, version)
+    }
+
+    /// The git tag of `package` at `version`: the package's own tag when `independent`,
+    /// otherwise the release tag.
+    pub fn package_tag_name(&self, package: &str, version: &str) -> String {
+        if self.independent {
+            render_template(self.tag_template(), package, version)
+        } else {
+            self.tag_name(version)
+        }
+    }
+
+    /// Regex matching the release's tags, capturing everything between the template's
+    /// prefix and suffix in the `version` group.
+    pub fn tag_regex(&self) -> Result<Regex> {
+        self.tag_regex_for(&self.name)
+    }
+
+    /// Like [`ReleaseSet::tag_regex`], for the tags of a single package when `independent`.
+    pub fn package_tag_regex(&self, package: &str) -> Result<Regex> {
+        if !self.independent {
+            return self.tag_regex();
+        }
+        let template = self.tag_template();
+        anyhow::ensure!(
+            template.contains("{name}"),
+            "tag_template `{template}` must contain `{{name}}` for an `independent` release"
+        );
+        self.tag_regex_for(package)
+    }
+
+    fn tag_regex_for(&self, name: &str) -> Result<Regex> {
+        let template = self.tag_template();
+        let Some((prefix, suffix)) = template.split_once("{version}") else {
+            anyhow::bail!("tag_template `{template}` does not contain `{{version}}`");
+        };
+        let prefix = regex::escape(&render_template(prefix, name, ""));
+        let suffix = regex::escape(&render_template(suffix, name, ""));
+        Regex::new(&format!(r"^{prefix}(?<version>.+){suffix}$")).context("failed to make regex")
+    }
+
+    fn tag_template(&self) -> &str {
+        self.tag_template.as_deref().unwrap_or(DEFAULT_TAG_TEMPLATE)
+    }
+
+    pub fn commit_message(&self, version: &str) -> String {
+        render_template(self.commit_message_template(), &self.name, version)
+    }
+
+    fn commit_message_template(&self) -> &str {
+        self.commit_message.as_deref().unwrap_or(DEFAULT_COMMIT_MESSAGE)
+    }
+
+    /// The artifact directory: `output_dir` (from `--output-dir`), the configured
+    /// `output_dir` relative to the workspace root of `cwd`, or cargo's target directory.
+    /// Created if missing.
+    pub fn output_dir(&self, cwd: &Path, output_dir: Option<&Path>) -> Result<PathBuf> {
+        let dir = if let Some(dir) = output_dir {
+            dir.to_path_buf()
+        } else {
+            let metadata = MetadataCommand::new().current_dir(cwd).no_deps().exec()?;
+            match &self.output_dir {
+                Some(dir) => metadata.workspace_root.as_std_path().join(dir),
+                None => metadata.target_directory.into_std_path_buf(),
+            }
+        };
+        fs::create_dir_all(&dir).with_context(|| format!("failed to create {}", dir.display()))?;
+        Ok(dir)
+    }
+
+    /// Commit message of an `independent` update: `commit_message` rendered with `{name}` set
+    /// to each bumped package, the first as the subject and the others as the body.
+    pub fn independent_commit_message(&self, versions: &BTreeMap<PackageId, String>) -> String {
+        let template = self.commit_message_template();
+        let mut messages = names_and_versions(versions)
+            .into_iter()
+            .map(|(package, version)| render_template(template, package, version));
+        let subject = messages.next().unwrap_or_default();
+        let body = messages.collect::<Vec<_>>();
+        if body.is_empty() { subject } else { format!("{subject}\n\n{}", body.join("\n")) }
     }
 }
 
+/// The distinct `(name, version)` pairs of `versions`: a crate and an npm package of the same
+/// name bumped to the same version share one tag.
+#[must_use]
+pub fn names_and_versions(versions: &BTreeMap<PackageId, String>) -> Vec<(&str, &str)> {
+    let mut names = versions
+        .iter()
+        .map(|(id, version)| (id.name.as_str(), version.as_str()))
+        .collect::<Vec<_>>();
+    names.sort_unstable();
+    names.dedup();
+    names
+}
+
+/// Fill the `{name}` and `{version}` placeholders of a configured template.
+#[allow(clippy::literal_string_with_formatting_args)]
+fn render_template(template: &str, name: &str, version: &str) -> String {
+    template.replace("{name}", name).replace("{version}", version)
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(transparent)]
 pub struct VersionedFile {
@@ -94,20 +299,52 @@ pub enum VersionedContent {
     PackageJson(PackageJson),
 }
 
+/// Identifies a package of a release: a crate and an npm package may share a name,
+/// like `oxlint`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PackageId {
+    pub kind: PackageKind,
+    pub name: String,
+}
+
+impl PackageId {
+    pub fn new(kind: PackageKind, name: &str) -> Self {
+        Self { kind, name: name.to_string() }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct VersionedPackage {
+    pub kind: PackageKind,
     pub name: String,
+    pub version: String,
     pub dir: PathBuf,
     pub path: PathBuf,
+    /// Names of the packages this one depends on, excluding dev-dependencies.
+    pub dependencies: Vec<String>,
+    /// Manifest of the workspace this package inherits `version.workspace = true` from.
+    pub workspace_version: Option<PathBuf>,
+}
+
+impl VersionedPackage {
+    #[must_use]
+    pub fn id(&self) -> PackageId {
+        PackageId::new(self.kind, &self.name)
+    }
+
+    /// Whether this package depends on a package in `versions`.
+    pub fn depends_on_any(&self, versions: &BTreeMap<PackageId, String>) -> bool {
+        self.dependencies.iter().any(|name| versions.contains_key(&PackageId::new(self.kind, name)))
+    }
 }
 
 impl VersionedContent {
-    fn read(path: &Path) -> Result<Self> {
+    fn read(path: &Path, add_workspace_dependencies: bool) -> Result<Self> {
         let file_name = path
             .file_name()
             .with_context(|| format!("{} does not have a filename.", path.display()))?;
         let content = match file_name.to_string_lossy().as_ref() {
-            "Cargo.toml" => Self::Cargo(CargoToml::new(path)?),
+            "Cargo.toml" => Self::Cargo(CargoToml::new(path, add_workspace_dependencies)?),
             "package.json" => Self::PackageJson(PackageJson::new(path)?),
             _ => anyhow::bail!("{} is not recognized", path.display()),
         };
@@ -123,11 +360,19 @@ impl VersionedContent {
         }
     }
 
-    pub fn update_version(&self, version: &str) -> Result<()> {
+    pub fn update_versions(
+        &self,
+        versions: &BTreeMap<PackageId, String>,
+        changes: &mut Changes,
+    ) -> Result<()> {
         match self {
             Self::None => Ok(()),
-            Self::Cargo(cargo) => cargo.update_version(version),
-            Self::PackageJson(package_json) => package_json.update_version(version),
+            Self::Cargo(cargo) => cargo.update_versions(versions, changes),
+            Self::PackageJson(package_json) => {
+                let id = PackageId::new(PackageKind::Npm, package_json.name());
+                let Some(version) = versions.get(&id) else { return Ok(()) };
+                package_json.update_version(version, changes)
+            }
         }
     }
 }
@@ -0,0 +1,51 @@
+use std::{path::Path, process::Command};
+
+use anyhow::{Context, Result};
+
+/// Run `git` with `args` in `path` and return its trimmed stdout.
+pub fn git(path: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .current_dir(path)
+        .args(args)
+        .output()
+        .with_context(|| format!("cannot run `git {}`", args.join(" ")))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "`git {}` failed:\n{}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim(),
+        );
+    }
+    Ok(String::from_utf8(output.stdout)?.trim().to_string())
+}
+
+pub fn head(path: &Path) -> Result<String> {
+    git(path, &["rev-parse", "HEAD"])
+}
+
+/// Commit only `files` with `message`, whatever else is staged.
+pub fn commit(path: &Path, files: &[&Path], message: &str) -> Result<()> {
+    let files = files.iter().map(|file| file.to_str().unwrap()).collect::<Vec<_>>();
+    // New files have to be added first, but `--only` keeps anything else that is already
+    // staged out of the release commit.
+    git(path, &[["add", "--"].as_slice(), &files].concat())?;
+    git(path, &[["commit", "--only", "--message", message, "--"].as_slice(), &files].concat())?;
+    Ok(())
+}
+
+/// Create an annotated tag `name` at `HEAD`.
+pub fn tag(path: &Path, name: &str) -> Result<()> {
+    git(path, &["tag", "--annotate", name, "--message", name])?;
+    Ok(())
+}
+
+/// Check out `rev` into a new detached worktree at `dir`.
+pub fn worktree_add(path: &Path, dir: &Path, rev: &str) -> Result<()> {
+    git(path, &["worktree", "add", "--detach", dir.to_str().unwrap(), rev])?;
+    Ok(())
+}
+
+pub fn worktree_remove(path: &Path, dir: &Path) -> Result<()> {
+    git(path, &["worktree", "remove", "--force", dir.to_str().unwrap()])?;
+    Ok(())
+}
@@ -1,7 +1,16 @@
 mod cargo_command;
+mod changes;
 mod config;
+mod git;
+mod npm_command;
+mod plan;
 mod publish;
+mod publish_report;
+mod publish_state;
+mod registry;
+mod semver_checks;
 mod update;
+mod version;
 mod versioning;
 
 use std::{
@@ -12,14 +21,15 @@ use std::{
 use anyhow::Result;
 use bpaf::Bpaf;
 
-pub use self::{publish::Publish, update::Update};
+pub use self::{plan::Plan, publish::Publish, update::Update, version::Bump};
 
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Debug, Clone, Bpaf)]
 pub struct Options {
     /// Select the release specified in `oxc_release.toml`.
     pub release: Vec<String>,
 
-    /// Run `cargo publish` with `--dry-run`
+    /// Run `cargo publish` with `--dry-run`; for `update`: print a diff instead of writing files.
     #[bpaf(switch, fallback(false))]
     pub dry_run: bool,
 
@@ -27,9 +37,58 @@ pub struct Options {
     #[bpaf(switch, fallback(false))]
     pub changelog: bool,
 
+    /// For `update`: commit the changed files.
+    #[bpaf(switch, fallback(false))]
+    pub commit: bool,
+
+    /// For `update`: create an annotated release tag (see `tag_template`) on the release commit.
+    #[bpaf(switch, fallback(false))]
+    pub git_tag: bool,
+
     /// For `update`: skip conventional-commit version detection and bump to this version.
     pub version: Option<String>,
 
+    /// For `update`: apply a `major`, `minor` or `patch` increment instead of detecting it
+    /// from conventional commits.
+    #[bpaf(argument("LEVEL"))]
+    pub bump: Option<Bump>,
+
+    /// For `update`: bump to a pre-release on this channel, e.g. `beta` for `1.3.0-beta.0`.
+    #[bpaf(argument("CHANNEL"))]
+    pub pre_release: Option<String>,
+
+    /// For `publish`: number of crates to publish concurrently within a dependency level.
+    #[bpaf(argument("N"), fallback(1))]
+    pub jobs: usize,
+
+    /// For `publish`: continue from the publish journal of a previous, failed run.
+    #[bpaf(switch, fallback(false))]
+    pub resume: bool,
+
+    /// For `publish`: attempts per crate when `cargo publish` fails with a rate limit,
+    /// server or network error.
+    #[bpaf(argument("N"), fallback(5))]
+    pub max_attempts: u32,
+
+    /// For `publish`: npm dist-tag to publish `package.json` packages under.
+    /// Defaults to the channel for pre-releases.
+    #[bpaf(argument("TAG"))]
+    pub tag: Option<String>,
+
+    /// For `publish`: write a JSON report of every package's outcome to this path.
+    #[bpaf(argument("PATH"))]
+    pub report: Option<PathBuf>,
+
+    /// For `update`, `changelog` and `publish`: directory for the `{NAME}_VERSION` and
+    /// `{NAME}_CHANGELOG` files and the publish journal. Defaults to `output_dir` in
+    /// `oxc_release.toml`, then to cargo's target directory.
+    #[bpaf(argument("PATH"))]
+    pub output_dir: Option<PathBuf>,
+
+    /// For `plan`: print JSON instead of a table.
+    #[bpaf(switch, fallback(false))]
+    pub json: bool,
+
     /// Optional path to directory, defaults to current working directory.
     #[bpaf(positional("PATH"), fallback_with(crate::current_dir))]
     pub path: PathBuf,
@@ -53,6 +112,11 @@ pub enum ReleaseCommand {
     /// Publish all `versioned_files` specified in `oxc_release.toml`.
     #[bpaf(command)]
     Publish(#[bpaf(external(options))] Options),
+
+    /// Show the next version, the packages to bump and what `publish` would release, without
+    /// changing anything.
+    #[bpaf(command)]
+    Plan(#[bpaf(external(options))] Options),
 }
 
 fn current_dir() -> Result<PathBuf, String> {
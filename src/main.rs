@@ -1,7 +1,7 @@
 use anyhow::Result;
 
 use cargo_release_oxc::{
-    Options, Publish, ReleaseCommand, Update, check_git_clean, release_command,
+    Options, Plan, Publish, ReleaseCommand, Update, check_git_clean, release_command,
 };
 
 fn main() -> Result<()> {
@@ -11,6 +11,7 @@ fn main() -> Result<()> {
         ReleaseCommand::Changelog(options) => changelog(&options),
         ReleaseCommand::RegenerateChangelogs(options) => regenerate_changelogs(&options),
         ReleaseCommand::Publish(options) => publish(&options),
+        ReleaseCommand::Plan(options) => plan(&options),
     }
 }
 
@@ -41,7 +42,26 @@ fn publish(options: &Options) -> Result<()> {
     let cwd = &options.path;
     check_git_clean(cwd)?;
     for release_name in &options.release {
-        Publish::new(cwd, release_name, options.dry_run)?.run()?;
+        Publish::new(cwd, release_name, options)?.run()?;
+    }
+    Ok(())
+}
+
+fn plan(options: &Options) -> Result<()> {
+    let plans = options
+        .release
+        .iter()
+        .map(|release_name| Plan::new(&options.path, release_name, options))
+        .collect::<Result<Vec<_>>>()?;
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(&plans)?);
+        return Ok(());
+    }
+    for (idx, plan) in plans.iter().enumerate() {
+        if idx > 0 {
+            println!();
+        }
+        plan.print();
     }
     Ok(())
 }
@@ -0,0 +1,84 @@
+use std::{
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use anyhow::{Context, Result};
+
+const DEFAULT_CLIENT: &str = "npm";
+
+/// Runs an npm-compatible client (`npm`, `pnpm`) against a configurable registry.
+pub struct NpmCommand {
+    client: String,
+    registry: Option<String>,
+}
+
+impl NpmCommand {
+    pub fn new(client: Option<&str>, registry: Option<&str>) -> Self {
+        Self {
+            client: client.unwrap_or(DEFAULT_CLIENT).to_string(),
+            registry: registry.map(ToString::to_string),
+        }
+    }
+
+    pub fn is_published(&self, dir: &Path, name: &str, version: &str) -> Result<bool> {
+        let spec = format!("{name}@{version}");
+        let output = self
+            .command(dir, &["view", &spec, "version"])
+            .output()
+            .with_context(|| format!("cannot run {}", self.client))?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if output.status.success() {
+            // `view` prints nothing when the package exists but the version does not.
+            return Ok(!String::from_utf8_lossy(&output.stdout).trim().is_empty());
+        }
+        if stderr.contains("E404") {
+            // Brand-new package, never published; not an error.
+            return Ok(false);
+        }
+        anyhow::bail!(
+            "`{} view {spec}` failed — cannot determine whether to publish or skip:\n{}",
+            self.client,
+            stderr.trim(),
+        );
+    }
+
+    pub fn publish(&self, dir: &Path, tag: Option<&str>, dry_run: bool) -> Result<()> {
+        let mut args = vec!["publish"];
+        if let Some(tag) = tag {
+            args.extend(["--tag", tag]);
+        }
+        if dry_run {
+            args.push("--dry-run");
+        }
+        if self.client == "pnpm" {
+            // The release runs from a tagged, possibly detached checkout.
+            args.push("--no-git-checks");
+        }
+        let status = self
+            .command(dir, &args)
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .status()
+            .with_context(|| format!("cannot run {}", self.client))?;
+        if !status.success() {
+            anyhow::bail!(
+                "`{} {}` failed in {} with exit code {}",
+                self.client,
+                args.join(" "),
+                dir.display(),
+                status.code().map_or_else(|| "?".to_string(), |c| c.to_string()),
+            );
+        }
+        Ok(())
+    }
+
+    fn command(&self, dir: &Path, args: &[&str]) -> Command {
+        let mut command = Command::new(&self.client);
+        command.current_dir(dir).args(args);
+        if let Some(registry) = &self.registry {
+            command.args(["--registry", registry]);
+        }
+        command
+    }
+}
@@ -0,0 +1,181 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::{Options, Publish, Update, config::PackageId, publish_report::PackageKind};
+
+#[derive(Debug, Serialize)]
+struct PlannedPackage {
+    name: String,
+    dir: PathBuf,
+    next_version: String,
+}
+
+#[derive(Debug, Serialize)]
+struct PlannedPublish {
+    name: String,
+    /// The version after `update`, which `publish` will release.
+    version: String,
+    /// Whether `version` is already on the registry, i.e. `publish` would skip it.
+    published: bool,
+}
+
+/// What `update` and `publish` would do for a release, without touching anything.
+#[derive(Debug, Serialize)]
+pub struct Plan {
+    release: String,
+    /// `None` for an `independent` release, whose packages each have their own version.
+    current_version: Option<String>,
+    next_version: Option<String>,
+    /// Failed `cargo semver-checks` checks, when `semver_checks` is enabled.
+    semver_findings: Vec<String>,
+    versioned_files: Vec<PathBuf>,
+    packages: Vec<PlannedPackage>,
+    registry: String,
+    /// Crates in publish order, grouped into levels that can be published concurrently.
+    publish_levels: Vec<Vec<PlannedPublish>>,
+    /// npm packages, published after the crates.
+    npm_packages: Vec<PlannedPublish>,
+}
+
+impl Plan {
+    pub fn new(cwd: &Path, release_name: &str, options: &Options) -> Result<Self> {
+        let update = Update::new(cwd, release_name)?;
+        let publish = Publish::new(&cwd.to_path_buf(), release_name, options)?;
+        let release_set = update.release_set();
+        let (current_version, next_version, versions) = if release_set.independent {
+            (None, None, update.next_versions(options)?)
+        } else {
+            let next_version = update.next_version(options)?;
+            let versions = update.lockstep_versions(&next_version)?;
+            (Some(update.current_version()?.to_string()), Some(next_version), versions)
+        };
+        let packages = release_set
+            .versioned_packages()
+            .into_iter()
+            .filter_map(|p| {
+                let next_version = versions.get(&p.id())?.clone();
+                Some(PlannedPackage { name: p.name, dir: relative(cwd, &p.dir), next_version })
+            })
+            .collect();
+        let publish_levels = publish
+            .publish_levels()?
+            .into_iter()
+            .map(|level| {
+                level
+                    .into_iter()
+                    .map(|package| {
+                        let name = package.name.to_string();
+                        let version = versions
+                            .get(&PackageId::new(PackageKind::Crate, &name))
+                            .cloned()
+                            .unwrap_or_else(|| package.version.to_string());
+                        let published = publish.is_published(&name, &version)?;
+                        Ok(PlannedPublish { name, version, published })
+                    })
+                    .collect::<Result<Vec<_>>>()
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let npm_packages = release_set
+            .npm_packages()
+            .into_iter()
+            .filter_map(|package| {
+                let name = package.name().to_string();
+                let version = versions
+                    .get(&PackageId::new(PackageKind::Npm, &name))
+                    .map(String::as_str)
+                    .or_else(|| package.version())?;
+                Some((package, name, version.to_string()))
+            })
+            .map(|(package, name, version)| {
+                let published = publish.is_npm_published(package, &version)?;
+                Ok(PlannedPublish { name, version, published })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            release: release_name.to_string(),
+            current_version,
+            next_version,
+            semver_findings: update.semver_findings(),
+            versioned_files: release_set.versioned_file_paths().map(Path::to_path_buf).collect(),
+            packages,
+            registry: publish.registry_name().to_string(),
+            publish_levels,
+            npm_packages,
+        })
+    }
+
+    pub fn print(&self) {
+        println!("Release:         {}", self.release);
+        if let (Some(current_version), Some(next_version)) =
+            (&self.current_version, &self.next_version)
+        {
+            println!("Current version: {current_version}");
+            println!("Next version:    {next_version}");
+        } else {
+            println!("Versioning:      independent");
+        }
+
+        if !self.semver_findings.is_empty() {
+            println!("\nSemver-checks findings:");
+            for finding in &self.semver_findings {
+                println!("  {finding}");
+            }
+        }
+
+        println!("\nVersioned files:");
+        for file in &self.versioned_files {
+            println!("  {}", file.display());
+        }
+
+        println!("\nPackages to bump ({}):", self.packages.len());
+        let width = self.packages.iter().map(|p| p.name.len()).max().unwrap_or(0);
+        let version_width = self.packages.iter().map(|p| p.next_version.len()).max().unwrap_or(0);
+        for package in &self.packages {
+            println!(
+                "  {:width$}  {:version_width$}  {}",
+                package.name,
+                package.next_version,
+                package.dir.display()
+            );
+        }
+
+        let total = self.publish_levels.iter().map(Vec::len).sum::<usize>();
+        println!("\nPublish order ({total} crate(s) in {} level(s)):", self.publish_levels.len());
+        let crates = self.publish_levels.iter().flatten();
+        let width = crates.clone().map(|c| c.name.len() + c.version.len() + 1).max().unwrap_or(0);
+        for (idx, level) in self.publish_levels.iter().enumerate() {
+            println!("  level {}:", idx + 1);
+            for krate in level {
+                let status = if krate.published {
+                    format!("already on {}", self.registry)
+                } else {
+                    "to publish".to_string()
+                };
+                let id = format!("{}@{}", krate.name, krate.version);
+                println!("    {id:width$}  {status}");
+            }
+        }
+
+        if !self.npm_packages.is_empty() {
+            println!("\nnpm packages ({}):", self.npm_packages.len());
+            let width = self
+                .npm_packages
+                .iter()
+                .map(|p| p.name.len() + p.version.len() + 1)
+                .max()
+                .unwrap_or(0);
+            for package in &self.npm_packages {
+                let status =
+                    if package.published { "already on the npm registry" } else { "to publish" };
+                let id = format!("{}@{}", package.name, package.version);
+                println!("  {id:width$}  {status}");
+            }
+        }
+    }
+}
+
+fn relative(cwd: &Path, path: &Path) -> PathBuf {
+    path.strip_prefix(cwd).unwrap_or(path).to_path_buf()
+}
@@ -1,35 +1,77 @@
 use std::{
+    collections::{BTreeMap, HashSet},
     fs,
     path::{Path, PathBuf},
-    time::Duration,
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
 use anyhow::{Context, Result};
 use cargo_metadata::{DependencyKind, Metadata, MetadataCommand, Package};
-use crates_io_api::SyncClient;
 
 use crate::{
+    Options,
     cargo_command::CargoCommand,
     config::{ReleaseConfig, ReleaseSet},
+    git,
+    npm_command::NpmCommand,
+    publish_report::{PackageKind, PublishReport},
+    publish_state::{Outcome, PublishState},
+    registry::Registry,
+    version,
+    versioning::package_json::PackageJson,
 };
 
+/// How long to wait for a published crate to show up on the registry.
+const INDEX_TIMEOUT: Duration = Duration::from_secs(300);
+const INDEX_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
 pub struct Publish {
     release_set: ReleaseSet,
     metadata: Metadata,
     cargo: CargoCommand,
-    client: SyncClient,
+    npm: NpmCommand,
+    registry: Registry,
     dry_run: bool,
+    jobs: usize,
+    resume: bool,
+    tag: Option<String>,
+    report: Option<PathBuf>,
+    output_dir: Option<PathBuf>,
 }
 
 impl Publish {
-    pub fn new(cwd: &PathBuf, release_name: &str, dry_run: bool) -> Result<Self> {
+    pub fn new(cwd: &PathBuf, release_name: &str, options: &Options) -> Result<Self> {
+        anyhow::ensure!(options.jobs > 0, "`--jobs` must be at least 1");
+        anyhow::ensure!(options.max_attempts > 0, "`--max-attempts` must be at least 1");
         let release_set = ReleaseConfig::new(cwd)?.get_release(release_name)?;
 
         let metadata = MetadataCommand::new().current_dir(cwd).no_deps().exec()?;
-        let cargo = CargoCommand::new(metadata.workspace_root.clone().into_std_path_buf());
-        let client = SyncClient::new("Boshen@users.noreply.github.com", Duration::from_secs(1))
-            .context("failed to get client")?;
-        Ok(Self { release_set, metadata, cargo, client, dry_run })
+        let cargo = CargoCommand::new(
+            metadata.workspace_root.clone().into_std_path_buf(),
+            options.max_attempts,
+        );
+        let npm =
+            NpmCommand::new(release_set.npm_client.as_deref(), release_set.npm_registry.as_deref());
+        let registry =
+            Registry::new(cwd, release_set.registry.as_deref(), release_set.index_url.as_deref())?;
+        Ok(Self {
+            release_set,
+            metadata,
+            cargo,
+            npm,
+            registry,
+            dry_run: options.dry_run,
+            jobs: options.jobs,
+            resume: options.resume,
+            tag: options.tag.clone(),
+            report: options.report.clone(),
+            output_dir: options.output_dir.clone(),
+        })
     }
 
     pub fn run(self) -> Result<()> {
@@ -50,34 +92,109 @@ impl Publish {
         validate_packages(&packages)?;
 
         let packages = release_order::release_order(&packages)?;
+
+        let output_dir = self
+            .release_set
+            .output_dir(self.metadata.workspace_root.as_std_path(), self.output_dir.as_deref())?;
+        let mut report = PublishReport::new(self.release_set.name.clone(), self.dry_run);
+        let result = self
+            .publish_crates(&packages, &output_dir, &mut report)
+            .and_then(|()| self.publish_npm_packages(&mut report));
+        report.record_not_attempted(
+            PackageKind::Crate,
+            packages.iter().map(|p| (p.name.as_str(), p.version.to_string())),
+        );
+        report.record_not_attempted(
+            PackageKind::Npm,
+            self.release_set
+                .npm_packages()
+                .into_iter()
+                .map(|p| (p.name(), p.version().unwrap_or_default().to_string())),
+        );
+        // The report matters most when publishing failed, so write it before bailing.
+        let saved = self.report.as_deref().map(|path| report.save(path)).transpose();
+        result?;
+        saved?;
+
+        let release_name = &self.release_set.name;
+        let version = self.release_set.package_tag_name(root_crate, &root_version);
+        let var = format!("{}_VERSION", release_name.to_uppercase());
+        let file = output_dir.join(var);
+        fs::write(file, version)?;
+        Ok(())
+    }
+
+    fn publish_crates(
+        &self,
+        packages: &[&Package],
+        output_dir: &Path,
+        report: &mut PublishReport,
+    ) -> Result<()> {
+        let levels = release_order::release_levels(packages);
+        let dependencies = release_order::release_dependencies(packages);
         let names: Vec<&str> = packages.iter().map(|p| p.name.as_str()).collect();
 
         let total = packages.len();
-        eprintln!("Publishing {total} package(s):");
-        for name in &names {
-            eprintln!("  - {name}");
+        eprintln!("Publishing {total} package(s) in {} level(s):", levels.len());
+        for (idx, level) in levels.iter().enumerate() {
+            eprintln!("  level {}:", idx + 1);
+            for package in level {
+                eprintln!("    - {}", package.name);
+            }
         }
 
+        // Nothing is uploaded in a dry run, so there is nothing worth journaling.
+        let state = if self.dry_run {
+            None
+        } else {
+            Some(Mutex::new(self.load_state(packages, output_dir)?))
+        };
+
+        let progress = AtomicUsize::new(0);
         let mut published: Vec<&str> = vec![];
         let mut skipped: Vec<&str> = vec![];
-        for (idx, package) in packages.iter().enumerate() {
-            let name = package.name.as_str();
-            // Check each package's own version: workspaces may pin crates to
-            // versions independent of the root crate.
-            let version = package.version.to_string();
-            eprintln!("\n[{}/{total}] {name}", idx + 1);
-            let summary = || publish_failure_summary(name, idx, &names, &published, &skipped);
-            if self.dry_run {
-                // check each crate individually to prevent feature unification.
-                self.cargo.check(name).with_context(summary)?;
+        for level in &levels {
+            let mut todo = vec![];
+            for package in level {
+                let name = package.name.as_str();
+                let version = package.version.to_string();
+                match state.as_ref().and_then(|state| state.lock().unwrap().outcome(name)) {
+                    Some(outcome) => {
+                        match outcome {
+                            Outcome::Published => published.push(name),
+                            Outcome::Skipped => skipped.push(name),
+                        }
+                        report.record(PackageKind::Crate, name, &version, &Ok(outcome), None);
+                    }
+                    None => todo.push(*package),
+                }
             }
-            if self.skip_published(name, &version)? {
-                skipped.push(name);
+            let resumed = level.len() - todo.len();
+            if resumed > 0 {
+                progress.fetch_add(resumed, Ordering::SeqCst);
+                eprintln!("\n{resumed} package(s) already done according to the publish journal");
+            }
+            let mut failures = vec![];
+            for (package, result, duration) in
+                self.publish_level(&todo, &dependencies, state.as_ref(), &progress, total)
+            {
+                let name = package.name.as_str();
+                let version = package.version.to_string();
+                report.record(PackageKind::Crate, name, &version, &result, Some(duration));
+                match result {
+                    Ok(Outcome::Published) => published.push(name),
+                    Ok(Outcome::Skipped) => skipped.push(name),
+                    Err(err) => failures.push((name, err)),
+                }
+            }
+            if failures.is_empty() {
                 continue;
             }
-            self.cargo.publish(name, self.dry_run).with_context(summary)?;
-            published.push(name);
-            eprintln!("  ✓ published");
+            let failed = failures.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+            let summary = publish_failure_summary(&failed, &names, &published, &skipped);
+            // Other failures in the same level already streamed their cargo output.
+            let (_, err) = failures.swap_remove(0);
+            return Err(err.context(summary));
         }
 
         eprintln!(
@@ -85,38 +202,206 @@ impl Publish {
             published.len(),
             skipped.len(),
         );
+        Ok(())
+    }
 
-        let release_name = &self.release_set.name;
-        let version = format!("{release_name}_v{root_version}");
-        let var = format!("{}_VERSION", release_name.to_uppercase());
-        let file = Path::new("./target").join(var);
-        fs::write(file, version)?;
+    fn publish_npm_packages(&self, report: &mut PublishReport) -> Result<()> {
+        let packages = self.release_set.npm_packages();
+        if packages.is_empty() {
+            return Ok(());
+        }
+        let total = packages.len();
+        eprintln!("\nPublishing {total} npm package(s):");
+        let mut published = 0;
+        for (idx, package) in packages.iter().enumerate() {
+            let name = package.name();
+            let version = package.version().with_context(|| format!("`{name}` has no version"))?;
+            eprintln!("\n[{}/{total}] {name}", idx + 1);
+            let start = Instant::now();
+            let result = self.publish_npm_package(package, version);
+            report.record(PackageKind::Npm, name, version, &result, Some(start.elapsed()));
+            if result? == Outcome::Published {
+                published += 1;
+            }
+        }
+        eprintln!(
+            "\nDone. {published} published, {} skipped (already on registry), {total} total.",
+            total - published,
+        );
         Ok(())
     }
 
-    fn skip_published(&self, package: &str, version: &str) -> Result<bool> {
-        match self.client.get_crate(package) {
-            Ok(krate) => {
-                let is_already_published = krate.versions.iter().any(|v| v.num == version);
-                if is_already_published {
-                    eprintln!("  · already on crates.io @ {version}, skipping");
+    fn publish_npm_package(&self, package: &PackageJson, version: &str) -> Result<Outcome> {
+        if self.is_npm_published(package, version)? {
+            eprintln!("  · already on the npm registry @ {version}, skipping");
+            return Ok(Outcome::Skipped);
+        }
+        // npm moves `latest` to whatever is published without a tag, so keep
+        // pre-releases on their channel's dist-tag unless `--tag` says otherwise.
+        let tag = self.tag.clone().or_else(|| version::pre_release_channel(version));
+        self.npm.publish(package.dir(), tag.as_deref(), self.dry_run)?;
+        eprintln!("  ✓ published");
+        Ok(Outcome::Published)
+    }
+
+    /// Start a fresh publish journal, or continue the previous one with `--resume`.
+    fn load_state(&self, packages: &[&Package], output_dir: &Path) -> Result<PublishState> {
+        let head = git::head(self.metadata.workspace_root.as_std_path())?;
+        let versions = packages
+            .iter()
+            .map(|p| (p.name.to_string(), p.version.to_string()))
+            .collect::<BTreeMap<_, _>>();
+        let var = format!("{}_publish_state.json", self.release_set.name);
+        let path = output_dir.join(var);
+        if self.resume
+            && let Some(state) = PublishState::load(&path, &head, &versions)?
+        {
+            return Ok(state);
+        }
+        Ok(PublishState::new(path, head, versions))
+    }
+
+    /// Publish all packages of one dependency level with up to `jobs` workers.
+    /// Once a package fails, workers finish their in-flight package but do not
+    /// start new ones, so the remaining packages are reported as not attempted.
+    fn publish_level<'a>(
+        &self,
+        level: &[&'a Package],
+        dependencies: &HashSet<&str>,
+        state: Option<&Mutex<PublishState>>,
+        progress: &AtomicUsize,
+        total: usize,
+    ) -> Vec<(&'a Package, Result<Outcome>, Duration)> {
+        let next = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+        let results = Mutex::new(vec![]);
+        thread::scope(|scope| {
+            for _ in 0..self.jobs.min(level.len()) {
+                scope.spawn(|| {
+                    while !failed.load(Ordering::SeqCst) {
+                        let idx = next.fetch_add(1, Ordering::SeqCst);
+                        let Some(package) = level.get(idx) else { break };
+                        let name = package.name.as_str();
+                        let n = progress.fetch_add(1, Ordering::SeqCst) + 1;
+                        eprintln!("\n[{n}/{total}] {name}");
+                        let has_dependents = dependencies.contains(name);
+                        let start = Instant::now();
+                        let result = self.publish_package(package, has_dependents);
+                        let duration = start.elapsed();
+                        match (&result, state) {
+                            (Err(_), _) => failed.store(true, Ordering::SeqCst),
+                            (Ok(outcome), Some(state)) => {
+                                let recorded = state.lock().unwrap().record(name, *outcome);
+                                if let Err(err) = recorded {
+                                    eprintln!("  ! failed to update publish journal: {err:#}");
+                                }
+                            }
+                            (Ok(_), None) => {}
+                        }
+                        results.lock().unwrap().push((idx, *package, result, duration));
+                    }
+                });
+            }
+        });
+        let mut results = results.into_inner().unwrap();
+        results.sort_by_key(|(idx, ..)| *idx);
+        results
+            .into_iter()
+            .map(|(_, package, result, duration)| (package, result, duration))
+            .collect()
+    }
+
+    fn publish_package(&self, package: &Package, has_dependents: bool) -> Result<Outcome> {
+        let name = package.name.as_str();
+        // Check each package's own version: workspaces may pin crates to
+        // versions independent of the root crate.
+        let version = package.version.to_string();
+        if self.dry_run {
+            // check each crate individually to prevent feature unification.
+            self.cargo.check(name)?;
+        }
+        if self.skip_published(name, &version)? {
+            return Ok(Outcome::Skipped);
+        }
+        self.cargo.publish(name, self.release_set.registry.as_deref(), self.dry_run)?;
+        eprintln!("  ✓ published {name}");
+        // Dependents published right after would fail to resolve a version the index
+        // has not caught up with yet; leaf crates have nobody waiting on them.
+        // A static `file://` index never catches up, so there is nothing to wait for.
+        if has_dependents && !self.dry_run && !self.registry.is_static() {
+            self.wait_until_visible(name, &version)?;
+        }
+        Ok(Outcome::Published)
+    }
+
+    fn wait_until_visible(&self, package: &str, version: &str) -> Result<()> {
+        let registry = self.registry.name();
+        let start = Instant::now();
+        loop {
+            match self.registry.versions(package) {
+                Ok(Some(versions)) if versions.iter().any(|v| v == version) => {
+                    eprintln!("  · {package}@{version} is visible on {registry}");
+                    return Ok(());
                 }
-                Ok(is_already_published)
+                Ok(_) => {}
+                // Keep polling through transient errors until the timeout.
+                Err(err) => eprintln!("  ! failed to query {registry} for `{package}`: {err}"),
             }
-            Err(crates_io_api::Error::NotFound(_)) => {
-                // Brand-new crate, never published; not an error.
-                eprintln!("  · not yet on crates.io (new crate)");
-                Ok(false)
+            if start.elapsed() >= INDEX_TIMEOUT {
+                anyhow::bail!(
+                    "`{package}@{version}` was published but did not appear on {registry} \
+                     within {}s",
+                    INDEX_TIMEOUT.as_secs(),
+                );
             }
-            Err(err) => Err(err).with_context(|| {
-                format!(
-                    "failed to query crates.io for `{package}` — \
-                     cannot determine whether to publish or skip"
-                )
-            }),
+            thread::sleep(INDEX_POLL_INTERVAL);
         }
     }
 
+    /// Crates in the order `run` publishes them, grouped into dependency levels.
+    pub fn publish_levels(&self) -> Result<Vec<Vec<&Package>>> {
+        let packages = self.get_packages();
+        let packages = release_order::release_order(&packages)?;
+        Ok(release_order::release_levels(&packages))
+    }
+
+    #[must_use]
+    pub fn registry_name(&self) -> &str {
+        self.registry.name()
+    }
+
+    pub fn is_published(&self, package: &str, version: &str) -> Result<bool> {
+        let versions = self
+            .registry
+            .versions(package)
+            .with_context(|| format!("failed to query {} for `{package}`", self.registry.name()))?;
+        Ok(versions.is_some_and(|versions| versions.iter().any(|v| v == version)))
+    }
+
+    pub fn is_npm_published(&self, package: &PackageJson, version: &str) -> Result<bool> {
+        self.npm.is_published(package.dir(), package.name(), version)
+    }
+
+    fn skip_published(&self, package: &str, version: &str) -> Result<bool> {
+        let registry = self.registry.name();
+        let versions = self.registry.versions(package).with_context(|| {
+            format!(
+                "failed to query {registry} for `{package}` — \
+                 cannot determine whether to publish or skip"
+            )
+        })?;
+        let Some(versions) = versions else {
+            // Brand-new crate, never published; not an error.
+            eprintln!("  · {package} not yet on {registry} (new crate)");
+            return Ok(false);
+        };
+        let is_already_published = versions.iter().any(|v| v == version);
+        if is_already_published {
+            eprintln!("  · {package} already on {registry} @ {version}, skipping");
+        }
+        Ok(is_already_published)
+    }
+
     fn get_packages(&self) -> Vec<&Package> {
         // `publish.is_none()` means `publish = true`.
         self.metadata.workspace_packages().into_iter().filter(|p| p.publish.is_none()).collect()
@@ -169,16 +454,21 @@ fn validate_packages(packages: &[&Package]) -> Result<()> {
 }
 
 fn publish_failure_summary(
-    failed: &str,
-    failed_idx: usize,
+    failed: &[&str],
     all: &[&str],
     published: &[&str],
     skipped: &[&str],
 ) -> String {
     use std::fmt::Write;
-    let remaining = &all[failed_idx + 1..];
-    let mut out =
-        format!("publish failed at `{failed}` ({}/{} in order)", failed_idx + 1, all.len());
+    let remaining = all
+        .iter()
+        .filter(|name| ![failed, published, skipped].iter().any(|list| list.contains(name)))
+        .collect::<Vec<_>>();
+    let position = all.iter().position(|name| *name == failed[0]).map_or(0, |idx| idx + 1);
+    let mut out = format!("publish failed at `{}` ({position}/{} in order)", failed[0], all.len());
+    if failed.len() > 1 {
+        write!(out, "\n  also failed in the same level: {:?}", &failed[1..]).unwrap();
+    }
     if !published.is_empty() {
         write!(out, "\n  published before failure: {published:?}").unwrap();
     }
@@ -192,13 +482,15 @@ fn publish_failure_summary(
 }
 
 mod release_order {
+    use std::collections::{HashMap, HashSet};
+
     use anyhow::Result;
     use cargo_metadata::Package;
 
     /// Return packages in an order they can be released.
     /// In the result, the packages are placed after all their dependencies.
     /// Return an error if a circular dependency is detected.
-    pub fn release_order<'a>(packages: &'a [&Package]) -> Result<Vec<&'a Package>> {
+    pub fn release_order<'a>(packages: &[&'a Package]) -> Result<Vec<&'a Package>> {
         let mut order = vec![];
         let mut passed = vec![];
         for p in packages {
@@ -207,6 +499,42 @@ mod release_order {
         Ok(order)
     }
 
+    /// Group packages returned by [`release_order`] into levels.
+    /// Every package only depends on packages in earlier levels,
+    /// so the packages within one level can be released concurrently.
+    pub fn release_levels<'a>(order: &[&'a Package]) -> Vec<Vec<&'a Package>> {
+        let mut levels: Vec<Vec<&Package>> = vec![];
+        let mut depths: HashMap<&str, usize> = HashMap::new();
+        for pkg in order {
+            let depth = pkg
+                .dependencies
+                .iter()
+                .filter(|d| d.name != pkg.name.as_str())
+                .filter_map(|d| depths.get(d.name.as_str()))
+                .map(|depth| depth + 1)
+                .max()
+                .unwrap_or(0);
+            depths.insert(pkg.name.as_str(), depth);
+            if levels.len() <= depth {
+                levels.resize_with(depth + 1, Vec::new);
+            }
+            levels[depth].push(pkg);
+        }
+        levels
+    }
+
+    /// Names of packages that other packages in the release depend on.
+    pub fn release_dependencies<'a>(packages: &[&'a Package]) -> HashSet<&'a str> {
+        packages
+            .iter()
+            .flat_map(|pkg| {
+                pkg.dependencies.iter().filter(|d| d.name != pkg.name.as_str()).filter_map(|d| {
+                    packages.iter().find(|p| d.name == p.name.as_str()).map(|p| p.name.as_str())
+                })
+            })
+            .collect()
+    }
+
     /// The `passed` argument is used to track packages that you already visited to
     /// detect circular dependencies.
     fn release_order_inner<'a>(
@@ -0,0 +1,112 @@
+use std::{fs, path::Path, time::Duration};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::publish_state::Outcome;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReportOutcome {
+    Published,
+    Skipped,
+    Failed,
+    NotAttempted,
+}
+
+impl From<Outcome> for ReportOutcome {
+    fn from(outcome: Outcome) -> Self {
+        match outcome {
+            Outcome::Published => Self::Published,
+            Outcome::Skipped => Self::Skipped,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PackageKind {
+    Crate,
+    Npm,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PackageReport {
+    name: String,
+    version: String,
+    kind: PackageKind,
+    outcome: ReportOutcome,
+    /// Seconds spent on the package, `None` if it was not worked on in this run.
+    duration_secs: Option<f64>,
+    error: Option<String>,
+}
+
+/// Machine-readable summary of a `publish` run, written with `--report <path>`.
+#[derive(Debug, Serialize)]
+pub struct PublishReport {
+    release: String,
+    dry_run: bool,
+    packages: Vec<PackageReport>,
+}
+
+impl PublishReport {
+    pub const fn new(release: String, dry_run: bool) -> Self {
+        Self { release, dry_run, packages: vec![] }
+    }
+
+    pub fn record(
+        &mut self,
+        kind: PackageKind,
+        name: &str,
+        version: &str,
+        result: &Result<Outcome>,
+        duration: Option<Duration>,
+    ) {
+        let (outcome, error) = match result {
+            Ok(outcome) => (ReportOutcome::from(*outcome), None),
+            Err(err) => (ReportOutcome::Failed, Some(strip_ansi(&format!("{err:#}")))),
+        };
+        self.packages.push(PackageReport {
+            name: name.to_string(),
+            version: version.to_string(),
+            kind,
+            outcome,
+            duration_secs: duration.map(|d| d.as_secs_f64()),
+            error,
+        });
+    }
+
+    /// Record every package of `kind` without an entry yet as not attempted.
+    /// A crate and an npm package may share a name, so entries are matched by both.
+    pub fn record_not_attempted<'a>(
+        &mut self,
+        kind: PackageKind,
+        packages: impl IntoIterator<Item = (&'a str, String)>,
+    ) {
+        for (name, version) in packages {
+            if self.packages.iter().any(|p| p.kind == kind && p.name == name) {
+                continue;
+            }
+            self.packages.push(PackageReport {
+                name: name.to_string(),
+                version,
+                kind,
+                outcome: ReportOutcome::NotAttempted,
+                duration_secs: None,
+                error: None,
+            });
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).context("failed to write json")?;
+        fs::write(path, json).with_context(|| format!("failed to write {}", path.display()))?;
+        Ok(())
+    }
+}
+
+/// Cargo's output is captured with `--color always`; keep escape codes out of the report.
+fn strip_ansi(s: &str) -> String {
+    let ansi = regex::Regex::new(r"\x1b\[[0-9;]*m").unwrap();
+    ansi.replace_all(s, "").into_owned()
+}
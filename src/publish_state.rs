@@ -0,0 +1,89 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Outcome {
+    Published,
+    Skipped,
+}
+
+/// On-disk journal of a `publish` run, used by `--resume` to continue after a failure
+/// without asking the registry about every crate that already went through.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PublishState {
+    #[serde(skip)]
+    path: PathBuf,
+
+    /// `HEAD` of the repository when the journal was started.
+    head: String,
+
+    /// Version of every package in the release, keyed by package name.
+    versions: BTreeMap<String, String>,
+
+    /// Outcome of every finished package, keyed by package name.
+    outcomes: BTreeMap<String, Outcome>,
+}
+
+impl PublishState {
+    pub const fn new(path: PathBuf, head: String, versions: BTreeMap<String, String>) -> Self {
+        Self { path, head, versions, outcomes: BTreeMap::new() }
+    }
+
+    /// Load the journal at `path`, or `None` if it is missing or was written for a
+    /// different `HEAD` or different package versions.
+    pub fn load(
+        path: &Path,
+        head: &str,
+        versions: &BTreeMap<String, String>,
+    ) -> Result<Option<Self>> {
+        if !path.exists() {
+            eprintln!("No publish journal found at {}, starting from scratch.", path.display());
+            return Ok(None);
+        }
+        let s = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let mut state: Self = serde_json::from_str(&s)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+        if state.head != head {
+            eprintln!(
+                "Publish journal was written at {} but HEAD is {head}, starting from scratch.",
+                state.head
+            );
+            return Ok(None);
+        }
+        if &state.versions != versions {
+            eprintln!(
+                "Workspace versions changed since the publish journal was written, starting from scratch."
+            );
+            return Ok(None);
+        }
+        state.path = path.to_path_buf();
+        Ok(Some(state))
+    }
+
+    pub fn outcome(&self, package: &str) -> Option<Outcome> {
+        self.outcomes.get(package).copied()
+    }
+
+    pub fn record(&mut self, package: &str, outcome: Outcome) -> Result<()> {
+        self.outcomes.insert(package.to_string(), outcome);
+        self.save()
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(self).context("failed to write json")?;
+        fs::write(&self.path, json)
+            .with_context(|| format!("failed to write {}", self.path.display()))?;
+        Ok(())
+    }
+}
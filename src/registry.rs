@@ -0,0 +1,157 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+
+const CRATES_IO_INDEX: &str = "https://index.crates.io";
+
+/// The registry crates are published to and checked against.
+pub struct Registry {
+    name: String,
+    index: SparseIndex,
+}
+
+impl Registry {
+    /// crates.io, or the registry named `name` in cargo's configuration.
+    /// `index_url` overrides where the sparse index is read from, and names the registry
+    /// when `name` is not set.
+    pub fn new(cwd: &Path, name: Option<&str>, index_url: Option<&str>) -> Result<Self> {
+        let index = match (name, index_url) {
+            (_, Some(url)) => SparseIndex::new(url, None)?,
+            (Some(name), None) => SparseIndex::for_registry(cwd, name)?,
+            (None, None) => SparseIndex::new(CRATES_IO_INDEX, None)?,
+        };
+        let name = name.or(index_url).unwrap_or("crates.io").to_string();
+        Ok(Self { name, index })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the index is a local `file://` directory, which publishing does not update.
+    pub fn is_static(&self) -> bool {
+        self.index.url.starts_with("file://")
+    }
+
+    /// Published versions of `package`, or `None` if the registry does not know it.
+    pub fn versions(&self, package: &str) -> Result<Option<Vec<String>>> {
+        self.index.versions(package)
+    }
+}
+
+/// Reads crate versions from a cargo sparse registry index,
+/// served over HTTP or from a local directory with a `file://` URL.
+struct SparseIndex {
+    url: String,
+    token: Option<String>,
+    client: reqwest::blocking::Client,
+}
+
+impl SparseIndex {
+    fn new(url: &str, token: Option<String>) -> Result<Self> {
+        let client = reqwest::blocking::Client::builder()
+            .user_agent(concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")))
+            .timeout(Duration::from_secs(30))
+            .build()
+            .context("failed to get client")?;
+        Ok(Self { url: url.trim_end_matches('/').to_string(), token, client })
+    }
+
+    /// The index of the registry `name`, read from `CARGO_REGISTRIES_<NAME>_INDEX` or the
+    /// `[registries]` table of cargo's configuration files.
+    fn for_registry(cwd: &Path, name: &str) -> Result<Self> {
+        let var = format!("CARGO_REGISTRIES_{}", name.to_uppercase().replace('-', "_"));
+        let index = match env::var(format!("{var}_INDEX")) {
+            Ok(index) => index,
+            Err(_) => registry_index_from_config(cwd, name)?,
+        };
+        let Some(url) = index.strip_prefix("sparse+") else {
+            anyhow::bail!(
+                "registry `{name}` uses the git index protocol ({index}), only sparse indexes \
+                 are supported"
+            );
+        };
+        Self::new(url, env::var(format!("{var}_TOKEN")).ok())
+    }
+
+    fn versions(&self, package: &str) -> Result<Option<Vec<String>>> {
+        let url = format!("{}/{}", self.url, index_path(package));
+        if let Some(path) = url.strip_prefix("file://") {
+            let Ok(body) = fs::read_to_string(path) else { return Ok(None) };
+            return parse_index_file(&body)
+                .map(Some)
+                .with_context(|| format!("failed to parse {url}"));
+        }
+        let mut request = self.client.get(&url).header("cache-control", "no-cache");
+        if let Some(token) = &self.token {
+            request = request.header("authorization", token);
+        }
+        let response = request.send().with_context(|| format!("failed to fetch {url}"))?;
+        let status = response.status();
+        // Registries answer 404 or 410 for crates they have never seen.
+        if status == reqwest::StatusCode::NOT_FOUND || status == reqwest::StatusCode::GONE {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            anyhow::bail!("failed to fetch {url}: {status}");
+        }
+        let body = response.text().with_context(|| format!("failed to read {url}"))?;
+        parse_index_file(&body).map(Some).with_context(|| format!("failed to parse {url}"))
+    }
+}
+
+/// Path of a crate's file in the index, e.g. `se/rd/serde` or `3/s/syn`.
+fn index_path(package: &str) -> String {
+    let name = package.to_lowercase();
+    match name.len() {
+        1 => format!("1/{name}"),
+        2 => format!("2/{name}"),
+        3 => format!("3/{}/{name}", &name[..1]),
+        _ => format!("{}/{}/{name}", &name[..2], &name[2..4]),
+    }
+}
+
+/// An index file has one JSON object per published version.
+fn parse_index_file(content: &str) -> Result<Vec<String>> {
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let entry: serde_json::Value = serde_json::from_str(line)?;
+            entry
+                .get("vers")
+                .and_then(serde_json::Value::as_str)
+                .map(ToString::to_string)
+                .context("index entry without `vers`")
+        })
+        .collect()
+}
+
+fn registry_index_from_config(cwd: &Path, name: &str) -> Result<String> {
+    let cargo_home = env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cargo")));
+    // Cargo merges configuration from the current directory upwards, then `$CARGO_HOME`;
+    // the closest file wins.
+    let config_dirs = cwd.ancestors().map(|dir| dir.join(".cargo")).chain(cargo_home);
+    for dir in config_dirs {
+        for file in ["config.toml", "config"] {
+            let Ok(s) = fs::read_to_string(dir.join(file)) else { continue };
+            let config: toml::Table = toml::from_str(&s)
+                .with_context(|| format!("failed to parse {}", dir.join(file).display()))?;
+            let index = config
+                .get("registries")
+                .and_then(|registries| registries.get(name))
+                .and_then(|registry| registry.get("index"))
+                .and_then(toml::Value::as_str);
+            if let Some(index) = index {
+                return Ok(index.to_string());
+            }
+        }
+    }
+    anyhow::bail!("registry `{name}` is not configured in `[registries]` of any cargo config");
+}
@@ -0,0 +1,173 @@
+use std::{env, path::Path, process::Command};
+
+use anyhow::{Context, Result};
+use cargo_metadata::semver::Version;
+
+use crate::{git, version::Bump};
+
+/// Public API changes found by `cargo semver-checks` between a release tag and `HEAD`.
+#[derive(Debug, Default)]
+pub struct SemverReport {
+    /// The smallest semver bump the API changes require, `None` if they are compatible.
+    pub required: Option<Bump>,
+    /// Failed checks, as `<crate>: <check>: <description>`.
+    pub findings: Vec<String>,
+}
+
+impl SemverReport {
+    /// Run `cargo semver-checks` for `packages` against a worktree of `baseline`,
+    /// a commit of the previous release.
+    pub fn check(cwd: &Path, baseline: &str, packages: &[&str]) -> Result<Self> {
+        let worktree = env::temp_dir().join(format!("cargo-release-oxc-baseline-{baseline}"));
+        if worktree.exists() {
+            git::worktree_remove(cwd, &worktree)?;
+        }
+        git::worktree_add(cwd, &worktree, baseline)?;
+        let output = run_semver_checks(cwd, &worktree, packages);
+        git::worktree_remove(cwd, &worktree)?;
+        Ok(Self::parse(&output?))
+    }
+
+    fn parse(output: &str) -> Self {
+        let mut report = Self::default();
+        let mut package = "";
+        for line in output.lines().map(str::trim) {
+            if let Some(rest) = line.strip_prefix("Checking ") {
+                package = rest.split_whitespace().next().unwrap_or_default();
+            } else if let Some(failure) =
+                line.strip_prefix("--- failure ").and_then(|l| l.strip_suffix(" ---"))
+            {
+                report.findings.push(format!("{package}: {failure}"));
+            } else if line.contains("requires new major version") {
+                report.required = report.required.max(Some(Bump::Major));
+            } else if line.contains("requires new minor version") {
+                report.required = report.required.max(Some(Bump::Minor));
+            }
+        }
+        report
+    }
+
+    /// `next` raised to the bump the API changes of `current` require.
+    /// Before 1.0.0, breaking changes require a minor bump.
+    #[must_use]
+    pub fn raise(&self, current: &Version, next: Version) -> Version {
+        let required = match (self.required, current.major) {
+            (None, _) => return next,
+            (Some(Bump::Major), 0) => Bump::Minor,
+            (Some(bump), _) => bump,
+        };
+        let required = required.apply(current);
+        if required.cmp_precedence(&next).is_gt() { required } else { next }
+    }
+}
+
+/// Output of `cargo semver-checks`, which exits with an error when checks fail.
+fn run_semver_checks(cwd: &Path, baseline_root: &Path, packages: &[&str]) -> Result<String> {
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let mut command = Command::new(cargo);
+    command.current_dir(cwd).args(["semver-checks", "check-release", "--baseline-root"]);
+    command.arg(baseline_root).args(["--release-type", "patch"]);
+    for package in packages {
+        command.args(["--package", package]);
+    }
+    let output = command.output().context("cannot run `cargo semver-checks`, is it installed?")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let combined = format!("{stdout}\n{stderr}");
+    if !output.status.success() && !combined.contains("checks failed") {
+        anyhow::bail!("`cargo semver-checks` failed:\n{}", stderr.trim());
+    }
+    Ok(combined)
+}
+
+#[cfg(test)]
+mod tests {
+    use cargo_metadata::semver::Version;
+
+    use super::SemverReport;
+    use crate::version::Bump;
+
+    /// Output of `cargo semver-checks check-release` 0.36 for two crates.
+    const OUTPUT: &str = "\
+     Parsing oxc_span v0.30.0 (current)
+      Parsed [   0.912s] (current)
+     Parsing oxc_span v0.30.0 (baseline)
+      Parsed [   0.873s] (baseline)
+    Checking oxc_span v0.30.0 -> v0.30.0 (no change; assume patch)
+     Checked [   0.031s] 152 checks: 152 pass, 10 skip
+     Summary no semver update required
+    Finished [   1.845s] oxc_span
+     Parsing oxc_ast v0.30.0 (current)
+      Parsed [   2.101s] (current)
+     Parsing oxc_ast v0.30.0 (baseline)
+      Parsed [   2.054s] (baseline)
+    Checking oxc_ast v0.30.0 -> v0.30.0 (no change; assume patch)
+     Checked [   0.058s] 152 checks: 150 pass, 2 fail, 0 warn, 10 skip
+
+--- failure enum_variant_missing: pub enum variant removed or renamed ---
+
+Description:
+A publicly-visible enum has at least one variant that is no longer available under its prior name. It may have been renamed or removed entirely.
+        ref: https://doc.rust-lang.org/cargo/reference/semver.html#enum-variant-remove
+       impl: https://github.com/obi1kenobi/cargo-semver-checks/tree/v0.36.0/src/lints/enum_variant_missing.ron
+
+Failed in:
+  variant AstKind::TSImportEqualsDeclaration, previously in file /tmp/baseline/crates/oxc_ast/src/ast_kind.rs:12
+
+--- failure trait_method_added: pub trait method added ---
+
+Description:
+A non-sealed public trait added a new method without a default implementation, which breaks downstream implementations of the trait
+        ref: https://doc.rust-lang.org/cargo/reference/semver.html#trait-new-item-no-default
+       impl: https://github.com/obi1kenobi/cargo-semver-checks/tree/v0.36.0/src/lints/trait_method_added.ron
+
+Failed in:
+  trait method oxc_ast::visit::Visit::visit_decorator in file /root/oxc/crates/oxc_ast/src/visit.rs:40
+
+     Summary semver requires new major version: 2 major and 0 minor checks failed
+    Finished [   4.322s] oxc_ast
+";
+
+    #[test]
+    fn parses_failures() {
+        let report = SemverReport::parse(OUTPUT);
+        assert_eq!(report.required, Some(Bump::Major));
+        assert_eq!(
+            report.findings,
+            [
+                "oxc_ast: enum_variant_missing: pub enum variant removed or renamed",
+                "oxc_ast: trait_method_added: pub trait method added",
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_minor_and_passing_checks() {
+        let output = "\
+    Checking oxc_span v0.30.0 -> v0.30.0 (no change; assume patch)
+     Summary no semver update required
+    Checking oxc_ast v0.30.0 -> v0.30.0 (no change; assume patch)
+
+--- failure inherent_method_must_use_added: inherent method #[must_use] added ---
+
+     Summary semver requires new minor version: 0 major and 1 minor checks failed
+";
+        let report = SemverReport::parse(output);
+        assert_eq!(report.required, Some(Bump::Minor));
+        assert_eq!(
+            report.findings,
+            ["oxc_ast: inherent_method_must_use_added: inherent method #[must_use] added"]
+        );
+        assert!(SemverReport::parse(&OUTPUT[..OUTPUT.find("---").unwrap()]).required.is_none());
+    }
+
+    #[test]
+    fn raises_the_next_version() {
+        let report = SemverReport { required: Some(Bump::Major), findings: vec![] };
+        let current = Version::new(0, 30, 0);
+        assert_eq!(report.raise(&current, Version::new(0, 30, 1)), Version::new(0, 31, 0));
+        let current = Version::new(1, 2, 3);
+        assert_eq!(report.raise(&current, Version::new(1, 3, 0)), Version::new(2, 0, 0));
+        assert_eq!(report.raise(&current, Version::new(3, 0, 0)), Version::new(3, 0, 0));
+    }
+}
@@ -1,39 +1,52 @@
 use std::{
+    cell::RefCell,
+    collections::BTreeMap,
     fs::{self, File},
     path::{Path, PathBuf},
+    slice,
     time::{SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::{Context, Result};
+use cargo_metadata::{MetadataCommand, semver::Version};
 use git_cliff_core::{
     DEFAULT_CONFIG, changelog::Changelog, commit::Commit, config::Config, release::Release,
     repo::Repository,
 };
+use regex::Regex;
 
 use crate::{
     Options,
-    config::{ReleaseConfig, ReleaseSet, VersionedPackage},
+    changes::Changes,
+    config::{self, PackageId, ReleaseConfig, ReleaseSet, VersionedPackage},
+    git,
+    publish_report::PackageKind,
+    semver_checks::SemverReport,
+    version::{self, Bump},
 };
 
 const CHANGELOG_NAME: &str = "CHANGELOG.md";
 
 #[derive(Debug, Clone)]
 struct GitTag {
-    version: String,
+    version: Version,
     sha: String,
 }
 
 impl GitTag {
-    fn new(sha: String, tag: String) -> Result<Self> {
-        let version = if tag.contains('v') {
-            tag.split_once('v')
-                .with_context(|| format!("tag {tag} does not have a `v`"))?
-                .1
-                .to_string()
-        } else {
-            tag
-        };
-        Ok(Self { version, sha })
+    /// `tag_regex` is [`ReleaseSet::tag_regex`], which captures everything between the
+    /// template's prefix and suffix. Returns `None` with a warning if that is not semver.
+    fn new(sha: String, tag: &str, tag_regex: &Regex) -> Option<Self> {
+        let captured = &tag_regex.captures(tag)?["version"];
+        match Version::parse(captured) {
+            Ok(version) => Some(Self { version, sha }),
+            Err(err) => {
+                eprintln!(
+                    "warning: skipping tag `{tag}`: `{captured}` is not a valid version: {err}"
+                );
+                None
+            }
+        }
     }
 }
 
@@ -43,7 +56,11 @@ pub struct Update {
     git_cliff_repo: Repository,
     git_cliff_config: Config,
     tags: Vec<GitTag>,
+    /// Tags of each package of an `independent` release.
+    package_tags: BTreeMap<PackageId, Vec<GitTag>>,
     current_version: Option<String>,
+    /// Failed `cargo semver-checks` checks found while calculating versions.
+    semver_findings: RefCell<Vec<String>>,
 }
 
 impl Update {
@@ -54,71 +71,412 @@ impl Update {
 
         let git_cliff_repo = Repository::init(cwd.clone())?;
         let git_cliff_config = Config::load(&cwd.join(DEFAULT_CONFIG))?;
-        let tag_pattern = regex::Regex::new(&format!("^{release_name}_v[0-9]*"))
-            .context("failed to make regex")?;
-        let tags = git_cliff_repo
-            .tags(
-                &Some(tag_pattern),
-                /* topo_order */ false,
-                /* include only the tags that belong to the current branch. */ false,
-            )?
-            .into_iter()
-            .map(|(sha, tag)| GitTag::new(sha, tag.name))
-            .collect::<Result<Vec<_>>>()?;
-        let current_version = tags.last().map(|t| t.version.clone());
-        Ok(Self { cwd, release_set, git_cliff_repo, git_cliff_config, tags, current_version })
+        let tags = release_tags(&git_cliff_repo, &release_set.tag_regex()?)?;
+        let mut package_tags = BTreeMap::new();
+        if release_set.independent {
+            for package in release_set.versioned_packages() {
+                let tag_regex = release_set.package_tag_regex(&package.name)?;
+                package_tags.insert(package.id(), release_tags(&git_cliff_repo, &tag_regex)?);
+            }
+        }
+        let current_version = match tags.last() {
+            Some(tag) => Some(tag.version.to_string()),
+            // First release: nothing has been tagged yet.
+            None => match &release_set.initial_version {
+                Some(version) => Some(version.clone()),
+                None => root_crate_version(&release_set)?,
+            },
+        };
+        Ok(Self {
+            cwd,
+            release_set,
+            git_cliff_repo,
+            git_cliff_config,
+            tags,
+            package_tags,
+            current_version,
+            semver_findings: RefCell::default(),
+        })
+    }
+
+    #[must_use]
+    pub const fn release_set(&self) -> &ReleaseSet {
+        &self.release_set
+    }
+
+    /// The version of the latest release tag, or for a first release the configured
+    /// `initial_version` or the version of `root_crate`.
+    pub fn current_version(&self) -> Result<&str> {
+        self.current_version.as_deref().ok_or_else(|| {
+            anyhow::anyhow!(
+                "No `{}` git tag found, set `initial_version` or `root_crate` for a first release",
+                self.release_set.tag_name("*")
+            )
+        })
+    }
+
+    /// Commits of `next_version` since the latest release tag, or the whole history for a
+    /// first release. See [`range_start`].
+    fn commits_range(&self, next_version: &str) -> Option<String> {
+        range_start(&self.tags, next_version)
+            .map(|tag| self.release_set.commits_range(&tag.version.to_string()))
     }
 
-    fn current_version(&self) -> Result<&str> {
-        self.current_version
-            .as_deref()
-            .ok_or_else(|| anyhow::anyhow!("No `{}_v*` git tag found", self.release_set.name))
+    /// The tags of `package`: its own when `independent`, otherwise the release's.
+    fn package_tags(&self, package: &VersionedPackage) -> &[GitTag] {
+        self.package_tags.get(&package.id()).map_or(self.tags.as_slice(), Vec::as_slice)
+    }
+
+    /// Commits of `package` at `next_version` since its latest tag, or the whole history
+    /// before its first tag. See [`range_start`].
+    fn package_commits_range(
+        &self,
+        package: &VersionedPackage,
+        next_version: &str,
+    ) -> Option<String> {
+        range_start(self.package_tags(package), next_version).map(|tag| {
+            self.release_set.package_commits_range(&package.name, &tag.version.to_string())
+        })
+    }
+
+    /// The version of the latest tag of `package`, or the version in its manifest.
+    fn package_current_version(&self, package: &VersionedPackage) -> Result<Version> {
+        if let Some(tag) = self.package_tags(package).last() {
+            return Ok(tag.version.clone());
+        }
+        Version::parse(&package.version)
+            .with_context(|| format!("invalid version {} of {}", package.version, package.name))
     }
 
     /// The next version to bump to: `--version` if provided (with any leading `v` stripped),
-    /// otherwise computed from conventional commits since the latest `<release>_v*` tag.
+    /// `--bump` applied to the current version, or otherwise computed from conventional
+    /// commits since the latest release tag.
+    /// With `--pre-release <channel>` this is a `<version>-<channel>.<n>` pre-release;
+    /// a stable update after pre-releases promotes to their version.
     pub fn next_version(&self, options: &Options) -> Result<String> {
+        anyhow::ensure!(
+            options.version.is_none() || options.bump.is_none(),
+            "`--version` and `--bump` cannot be used together"
+        );
         if let Some(version) = &options.version {
-            return Ok(version.strip_prefix('v').unwrap_or(version).to_string());
+            let version = version.strip_prefix('v').unwrap_or(version);
+            let next = Version::parse(version)
+                .with_context(|| format!("`--version {version}` is not a valid semver version"))?;
+            // A first release may use the version already in the manifests.
+            if let Some(tag) = self.tags.last() {
+                anyhow::ensure!(
+                    next.cmp_precedence(&tag.version).is_gt(),
+                    "`--version {next}` must be greater than the current version {}",
+                    tag.version
+                );
+            }
+            return Ok(next.to_string());
+        }
+        let current_version = self.current_version()?;
+        let current = Version::parse(current_version)
+            .with_context(|| format!("invalid current version {current_version}"))?;
+        let next = Self::bump(&current, options, || self.calculate_next_version())?;
+        Ok(next.to_string())
+    }
+
+    /// `current` bumped by `--bump` or by `detect`, or promoted to its stable version if it is
+    /// a pre-release, and then turned into a pre-release with `--pre-release <channel>`.
+    fn bump(
+        current: &Version,
+        options: &Options,
+        detect: impl FnOnce() -> Result<String>,
+    ) -> Result<Version> {
+        let next = match options.bump {
+            Some(bump) => bump.apply(current),
+            None if current.pre.is_empty() => Version::parse(&detect()?)?,
+            None => version::stable(current),
+        };
+        match &options.pre_release {
+            Some(channel) => version::pre_release_version(current, next, channel),
+            None => Ok(next),
+        }
+    }
+
+    /// The version to bump each package to.
+    /// Every package gets [`Update::next_version`], unless the release is `independent`:
+    /// then only packages with commits since their latest tag are bumped, by their own
+    /// commits, along with the packages depending on them.
+    /// With `skip_unchanged`, packages without commits since the release tag keep their
+    /// version unless they depend on a bumped package.
+    pub fn next_versions(&self, options: &Options) -> Result<BTreeMap<PackageId, String>> {
+        if !self.release_set.independent {
+            return self.lockstep_versions(&self.next_version(options)?);
+        }
+        let packages = self.release_set.versioned_packages();
+        anyhow::ensure!(
+            options.version.is_none(),
+            "`--version` cannot be used with an `independent` release"
+        );
+        let mut versions = BTreeMap::new();
+        for package in &packages {
+            // Commits since the latest tag, whether it is a pre-release or not.
+            let current = self.package_current_version(package)?;
+            let commits_range = self.package_commits_range(package, &current.to_string());
+            let commits = self.get_commits_for_package(package, commits_range.as_deref())?;
+            if commits.is_empty() {
+                continue;
+            }
+            let next = Self::bump(&current, options, || {
+                let next = self.bump_from_commits(commits, &current.to_string())?;
+                self.check_semver(slice::from_ref(package), self.package_tags(package).last(), next)
+            })?;
+            versions.insert(package.id(), next.to_string());
+        }
+        Self::bump_dependents(&packages, &mut versions, |package| {
+            let current = self.package_current_version(package)?;
+            let next =
+                Self::bump(&current, options, || Ok(Bump::Patch.apply(&current).to_string()))?;
+            Ok(next.to_string())
+        })?;
+        Ok(versions)
+    }
+
+    /// The versions of a lockstep release bumping to `next_version`, see
+    /// [`Update::next_versions`].
+    pub fn lockstep_versions(&self, next_version: &str) -> Result<BTreeMap<PackageId, String>> {
+        let packages = self.release_set.versioned_packages();
+        if !self.release_set.skip_unchanged {
+            return Ok(packages.iter().map(|p| (p.id(), next_version.to_string())).collect());
+        }
+        let mut versions = BTreeMap::new();
+        for package in &packages {
+            let commits_range = self.package_commits_range(package, next_version);
+            if !self.get_commits_for_package(package, commits_range.as_deref())?.is_empty() {
+                versions.insert(package.id(), next_version.to_string());
+            }
+        }
+        // The release is versioned and tagged after `root_crate`, so it always moves along.
+        if let Some(root_crate) = &self.release_set.root_crate
+            && !versions.is_empty()
+        {
+            let root_crate = PackageId::new(PackageKind::Crate, root_crate);
+            if packages.iter().any(|p| p.id() == root_crate) {
+                versions.insert(root_crate, next_version.to_string());
+            }
+        }
+        Self::bump_dependents(&packages, &mut versions, |_| Ok(next_version.to_string()))?;
+        Ok(versions)
+    }
+
+    /// Bump every package depending on a bumped package to `next_version`, so it is
+    /// published again with the new dependency requirement.
+    /// Packages inheriting `version.workspace = true` from the same workspace share one
+    /// version: once one of them is bumped, they all get the highest of their versions.
+    fn bump_dependents(
+        packages: &[VersionedPackage],
+        versions: &mut BTreeMap<PackageId, String>,
+        mut next_version: impl FnMut(&VersionedPackage) -> Result<String>,
+    ) -> Result<()> {
+        loop {
+            let workspace_versions = Self::unify_workspace_versions(packages, versions);
+            let dependents = packages
+                .iter()
+                .filter(|package| {
+                    !versions.contains_key(&package.id())
+                        && (package.depends_on_any(versions)
+                            || package
+                                .workspace_version
+                                .as_ref()
+                                .is_some_and(|w| workspace_versions.contains_key(w)))
+                })
+                .collect::<Vec<_>>();
+            if dependents.is_empty() {
+                return Ok(());
+            }
+            for package in dependents {
+                versions.insert(package.id(), next_version(package)?);
+            }
+        }
+    }
+
+    /// Give the bumped packages inheriting the same workspace version the highest of their
+    /// versions, and return it for each workspace manifest.
+    fn unify_workspace_versions(
+        packages: &[VersionedPackage],
+        versions: &mut BTreeMap<PackageId, String>,
+    ) -> BTreeMap<PathBuf, String> {
+        let mut workspace_versions = BTreeMap::<PathBuf, Version>::new();
+        for package in packages {
+            let Some(workspace) = &package.workspace_version else { continue };
+            let Some(version) = versions.get(&package.id()) else { continue };
+            let Ok(version) = Version::parse(version) else { continue };
+            let highest =
+                workspace_versions.entry(workspace.clone()).or_insert_with(|| version.clone());
+            if version.cmp_precedence(highest).is_gt() {
+                *highest = version;
+            }
+        }
+        for package in packages {
+            let Some(workspace) = &package.workspace_version else { continue };
+            if let (Some(version), Some(highest)) =
+                (versions.get_mut(&package.id()), workspace_versions.get(workspace))
+            {
+                *version = highest.to_string();
+            }
+        }
+        workspace_versions.into_iter().map(|(w, v)| (w, v.to_string())).collect()
+    }
+
+    /// The version of an update: the release's version, or the `root_crate`'s version
+    /// when `independent`. `None` for an `independent` release without a `root_crate`.
+    fn release_version(&self, versions: &BTreeMap<PackageId, String>) -> Result<Option<String>> {
+        if !self.release_set.independent {
+            // All packages share the release's version.
+            return Ok(versions.values().next().cloned());
+        }
+        let Some(root_crate) = &self.release_set.root_crate else { return Ok(None) };
+        if let Some(version) = versions.get(&PackageId::new(PackageKind::Crate, root_crate)) {
+            return Ok(Some(version.clone()));
+        }
+        let packages = self.release_set.versioned_packages();
+        let Some(package) = packages.iter().find(|p| &p.name == root_crate) else {
+            return Ok(None);
+        };
+        Ok(Some(self.package_current_version(package)?.to_string()))
+    }
+
+    /// The tags of an update: the release tag, or one tag per bumped package when
+    /// `independent`.
+    fn tag_names(&self, versions: &BTreeMap<PackageId, String>) -> Result<Vec<String>> {
+        if self.release_set.independent {
+            return Ok(config::names_and_versions(versions)
+                .into_iter()
+                .map(|(package, version)| self.release_set.package_tag_name(package, version))
+                .collect());
+        }
+        Ok(self
+            .release_version(versions)?
+            .map(|v| self.release_set.tag_name(&v))
+            .into_iter()
+            .collect())
+    }
+
+    fn commit_message(&self, versions: &BTreeMap<PackageId, String>) -> Result<String> {
+        if self.release_set.independent {
+            return Ok(self.release_set.independent_commit_message(versions));
         }
-        self.calculate_next_version()
+        let version = self.release_version(versions)?.unwrap_or_default();
+        Ok(self.release_set.commit_message(&version))
     }
 
+    /// With `--dry-run`, print the changes as a unified diff instead of writing anything.
+    /// With `--commit` and `--git-tag`, commit the changed files and tag the release commit.
     pub fn run(&self, options: &Options) -> Result<()> {
-        let next_version = self.next_version(options)?;
-        if options.changelog {
-            self.print_changelog_for_release(&next_version)?;
+        anyhow::ensure!(!options.git_tag || options.commit, "`--git-tag` requires `--commit`");
+        let versions = self.next_versions(options)?;
+        if versions.is_empty() {
+            eprintln!("  · no package of `{}` changed since its last tag", self.release_set.name);
+            return Ok(());
         }
-        self.write_version_file(&next_version)?;
+        if !options.dry_run {
+            let output_dir =
+                self.release_set.output_dir(&self.cwd, options.output_dir.as_deref())?;
+            if options.changelog {
+                self.print_changelog_for_release(&versions, &output_dir)?;
+            }
+            if let Some(version) = self.release_version(&versions)? {
+                self.write_version_file(&version, &output_dir)?;
+            }
+        }
+        let mut changes = Changes::default();
         if options.changelog {
             for package in self.release_set.versioned_packages() {
-                self.generate_changelog_for_package(&package, &next_version)?;
+                if let Some(version) = versions.get(&package.id()) {
+                    self.generate_changelog_for_package(&package, version, &mut changes)?;
+                }
+            }
+        }
+        self.release_set.update_versions(&versions, &mut changes)?;
+        if options.dry_run {
+            print!("{}", changes.diff(&self.cwd));
+            return Ok(());
+        }
+        let changed_files = changes.paths().map(Path::to_path_buf).collect::<Vec<_>>();
+        changes.apply()?;
+        if options.commit {
+            let files = changed_files.iter().map(PathBuf::as_path).collect::<Vec<_>>();
+            git::commit(&self.cwd, &files, &self.commit_message(&versions)?)?;
+        }
+        if options.git_tag {
+            for tag in self.tag_names(&versions)? {
+                git::tag(&self.cwd, &tag)?;
             }
         }
-        self.release_set.update_version(&next_version)?;
         Ok(())
     }
 
-    pub fn changelog_for_release(&self, options: &Options) -> Result<String> {
-        let next_version = self.next_version(options)?;
-        self.print_changelog_for_release(&next_version)?;
-        self.write_version_file(&next_version)?;
-        Ok(next_version)
+    pub fn changelog_for_release(&self, options: &Options) -> Result<()> {
+        let versions = self.next_versions(options)?;
+        let output_dir = self.release_set.output_dir(&self.cwd, options.output_dir.as_deref())?;
+        self.print_changelog_for_release(&versions, &output_dir)?;
+        if let Some(version) = self.release_version(&versions)? {
+            self.write_version_file(&version, &output_dir)?;
+        }
+        Ok(())
     }
 
-    fn write_version_file(&self, next_version: &str) -> Result<()> {
+    fn write_version_file(&self, next_version: &str, output_dir: &Path) -> Result<()> {
         let var = format!("{}_VERSION", self.release_set.name.to_uppercase());
-        let file = Path::new("./target").join(var);
+        let file = output_dir.join(var);
         fs::write(file, next_version)?;
         Ok(())
     }
 
     fn calculate_next_version(&self) -> Result<String> {
-        let mut commits = self
-            .get_commits_for_release()?
-            .into_iter()
-            .filter_map(|c| c.into_conventional().ok())
+        let current_version = self.current_version()?;
+        let commits = self.get_commits_for_release(current_version)?;
+        let next = self.bump_from_commits(commits, current_version)?;
+        self.check_semver(&self.release_set.versioned_packages(), self.tags.last(), next)
+    }
+
+    /// `next` raised to the bump `cargo semver-checks` requires for the API changes of the
+    /// crates in `packages` since `baseline`, if `semver_checks` is enabled.
+    fn check_semver(
+        &self,
+        packages: &[VersionedPackage],
+        baseline: Option<&GitTag>,
+        next: String,
+    ) -> Result<String> {
+        if !self.release_set.semver_checks {
+            return Ok(next);
+        }
+        // A first release has no API to compare against.
+        let Some(baseline) = baseline else { return Ok(next) };
+        let crates = packages
+            .iter()
+            .filter(|package| package.path.ends_with("Cargo.toml"))
+            .map(|package| package.name.as_str())
             .collect::<Vec<_>>();
+        if crates.is_empty() {
+            return Ok(next);
+        }
+        let report = SemverReport::check(&self.cwd, &baseline.sha, &crates)?;
+        for finding in &report.findings {
+            eprintln!("  ! semver-checks: {finding}");
+        }
+        let raised = report.raise(&baseline.version, Version::parse(&next)?);
+        if raised.to_string() != next {
+            eprintln!("  ! semver-checks: raising the next version from {next} to {raised}");
+        }
+        self.semver_findings.borrow_mut().extend(report.findings);
+        Ok(raised.to_string())
+    }
+
+    /// Failed `cargo semver-checks` checks of the versions calculated so far.
+    #[must_use]
+    pub fn semver_findings(&self) -> Vec<String> {
+        self.semver_findings.borrow().clone()
+    }
+
+    /// The version after `current_version` according to the conventional `commits`.
+    fn bump_from_commits(&self, commits: Vec<Commit<'_>>, current_version: &str) -> Result<String> {
+        let mut commits =
+            commits.into_iter().filter_map(|c| c.into_conventional().ok()).collect::<Vec<_>>();
         // Only matching scopes can participate in braking change detection.
         if let Some(scopes) = &self.release_set.scopes_for_breaking_change {
             commits = commits
@@ -131,8 +489,7 @@ impl Update {
                 .collect::<Vec<_>>();
         }
 
-        let previous =
-            Release { version: Some(self.current_version()?.to_string()), ..Release::default() };
+        let previous = Release { version: Some(current_version.to_string()), ..Release::default() };
         let release = Release { commits, previous: Some(Box::new(previous)), ..Release::default() };
         let mut changelog = Changelog::new(vec![release], self.git_cliff_config.clone(), None)?;
         let next_version =
@@ -143,12 +500,12 @@ impl Update {
     fn get_commits_for_package(
         &self,
         package: &VersionedPackage,
-        commits_range: &str,
+        commits_range: Option<&str>,
     ) -> Result<Vec<Commit<'_>>> {
         let include_path = self.get_include_pattern(package)?;
         let commits = self
             .git_cliff_repo
-            .commits(Some(commits_range), Some(vec![include_path]), None, true)?
+            .commits(commits_range, Some(vec![include_path]), None, true)?
             .iter()
             .map(Commit::from)
             .collect::<Vec<_>>();
@@ -185,14 +542,19 @@ impl Update {
         })
     }
 
-    fn save_changelog(package_path: &Path, changelog: &Changelog) -> Result<()> {
+    fn save_changelog(
+        package_path: &Path,
+        changelog: &Changelog,
+        changes: &mut Changes,
+    ) -> Result<()> {
         if changelog.releases.is_empty() {
             return Ok(());
         }
         let changelog_path = package_path.join(CHANGELOG_NAME);
-        let prev_changelog_string = fs::read_to_string(&changelog_path).unwrap_or_default();
-        let mut out = File::create(&changelog_path)?;
+        let prev_changelog_string = changes.read_or_default(&changelog_path);
+        let mut out = vec![];
         changelog.prepend(prev_changelog_string, &mut out)?;
+        changes.write(&changelog_path, String::from_utf8(out)?);
         Ok(())
     }
 
@@ -200,20 +562,21 @@ impl Update {
         &self,
         package: &VersionedPackage,
         next_version: &str,
+        changes: &mut Changes,
     ) -> Result<()> {
-        let commits_range = self.release_set.commits_range(self.current_version()?);
-        let commits = self.get_commits_for_package(package, &commits_range)?;
+        let commits_range = self.package_commits_range(package, next_version);
+        let commits = self.get_commits_for_package(package, commits_range.as_deref())?;
         let release = self.get_git_cliff_release(commits, next_version, None)?;
         let mut config = self.git_cliff_config.clone();
         config.changelog.footer = None;
         let changelog = Changelog::new(vec![release], config, None)?;
-        Self::save_changelog(&package.dir, &changelog)?;
+        Self::save_changelog(&package.dir, &changelog, changes)?;
         Ok(())
     }
 
-    fn get_commits_for_release(&self) -> Result<Vec<Commit<'_>>> {
+    fn get_commits_for_release(&self, next_version: &str) -> Result<Vec<Commit<'_>>> {
         let release_set = &self.release_set;
-        let commits_range = release_set.commits_range(self.current_version()?);
+        let commits_range = self.commits_range(next_version);
         let include_paths = release_set
             .versioned_packages()
             .iter()
@@ -221,15 +584,49 @@ impl Update {
             .collect::<Result<Vec<_>>>()?;
         let commits = self
             .git_cliff_repo
-            .commits(Some(&commits_range), Some(include_paths), None, true)?
+            .commits(commits_range.as_deref(), Some(include_paths), None, true)?
             .iter()
             .map(Commit::from)
             .collect::<Vec<_>>();
         Ok(commits)
     }
 
-    fn print_changelog_for_release(&self, next_version: &str) -> Result<()> {
-        let commits = self.get_commits_for_release()?;
+    /// Release notes of an update, with a section per bumped package when `independent`,
+    /// followed by the `cargo semver-checks` findings.
+    fn print_changelog_for_release(
+        &self,
+        versions: &BTreeMap<PackageId, String>,
+        output_dir: &Path,
+    ) -> Result<()> {
+        let output = if self.release_set.independent {
+            let mut sections = vec![];
+            for package in self.release_set.versioned_packages() {
+                let Some(version) = versions.get(&package.id()) else { continue };
+                let commits_range = self.package_commits_range(&package, version);
+                let commits = self.get_commits_for_package(&package, commits_range.as_deref())?;
+                let notes = self.release_notes(commits, version)?;
+                let tag = self.release_set.package_tag_name(&package.name, version);
+                sections.push(format!("## {tag}\n\n{notes}"));
+            }
+            sections.join("\n\n")
+        } else {
+            let Some(version) = self.release_version(versions)? else { return Ok(()) };
+            self.release_notes(self.get_commits_for_release(&version)?, &version)?
+        };
+        let findings = self.semver_findings();
+        let output = if findings.is_empty() {
+            output
+        } else {
+            let findings = findings.iter().map(|f| format!("- {f}")).collect::<Vec<_>>();
+            format!("{output}\n\n### cargo semver-checks\n\n{}", findings.join("\n"))
+        };
+        let var = format!("{}_CHANGELOG", self.release_set.name.to_uppercase());
+        let file = output_dir.join(var);
+        fs::write(file, output)?;
+        Ok(())
+    }
+
+    fn release_notes(&self, commits: Vec<Commit<'_>>, next_version: &str) -> Result<String> {
         let release = self.get_git_cliff_release(commits, next_version, None)?;
         let mut git_cliff_config = self.git_cliff_config.clone();
         git_cliff_config.changelog.header = None;
@@ -237,25 +634,35 @@ impl Update {
         let changelog = Changelog::new(vec![release], git_cliff_config, None)?;
         let mut s = vec![];
         changelog.generate(&mut s).context("failed to generate changelog")?;
-        let var = format!("{}_CHANGELOG", self.release_set.name.to_uppercase());
-        let file = Path::new("./target").join(var);
         let output = String::from_utf8(s).unwrap();
         // remove the header date
         let output = output.split_once("\n\n").map_or_else(|| output.as_str(), |s| s.1).trim();
-        fs::write(file, output)?;
-        Ok(())
+        Ok(output.to_string())
     }
 
     pub fn regenerate_changelogs(&self) -> Result<()> {
+        // Stable releases absorb their pre-releases; only pre-releases after the latest
+        // stable release get entries of their own.
         for package in self.release_set.versioned_packages() {
+            let package_tags = self.package_tags(&package);
+            let latest_stable = package_tags.iter().rev().find(|tag| tag.version.pre.is_empty());
+            let tags = package_tags
+                .iter()
+                .filter(|tag| {
+                    tag.version.pre.is_empty()
+                        || latest_stable.is_none_or(|stable| {
+                            tag.version.cmp_precedence(&stable.version).is_gt()
+                        })
+                })
+                .collect::<Vec<_>>();
             let mut releases = vec![];
-            for pair in self.tags.windows(2) {
+            for pair in tags.windows(2) {
                 let from = &pair[0];
                 let to = &pair[1];
                 let commits_range = format!("{}..{}", from.sha, to.sha);
-                let commits = self.get_commits_for_package(&package, &commits_range)?;
+                let commits = self.get_commits_for_package(&package, Some(&commits_range))?;
                 let release =
-                    self.get_git_cliff_release(commits, &to.version.clone(), Some(&to.sha))?;
+                    self.get_git_cliff_release(commits, &to.version.to_string(), Some(&to.sha))?;
                 releases.push(release);
             }
             let changelog = Changelog::new(releases, self.git_cliff_config.clone(), None)?;
@@ -266,3 +673,94 @@ impl Update {
         Ok(())
     }
 }
+
+/// The tag the commits of `next_version` start after: the latest of `tags`, or for a stable
+/// version the latest stable one, so that it includes the commits of its pre-releases as in
+/// [`Update::regenerate_changelogs`].
+fn range_start<'a>(tags: &'a [GitTag], next_version: &str) -> Option<&'a GitTag> {
+    if Version::parse(next_version).is_ok_and(|version| version.pre.is_empty()) {
+        tags.iter().rev().find(|tag| tag.version.pre.is_empty())
+    } else {
+        tags.last()
+    }
+}
+
+/// Tags matching `tag_regex`, sorted by version precedence.
+fn release_tags(repo: &Repository, tag_regex: &Regex) -> Result<Vec<GitTag>> {
+    let mut tags = repo
+        .tags(
+            &Some(tag_regex.clone()),
+            /* topo_order */ false,
+            /* include only the tags that belong to the current branch. */ false,
+        )?
+        .into_iter()
+        .filter_map(|(sha, tag)| GitTag::new(sha, &tag.name, tag_regex))
+        .collect::<Vec<_>>();
+    // Git orders tags by commit time; the current version is the highest released one.
+    tags.sort_by(|a, b| a.version.cmp_precedence(&b.version));
+    Ok(tags)
+}
+
+/// The version in the manifest of the release's `root_crate`, if it has one.
+fn root_crate_version(release_set: &ReleaseSet) -> Result<Option<String>> {
+    let Some(root_crate) = &release_set.root_crate else { return Ok(None) };
+    let Some(package) =
+        release_set.versioned_packages().into_iter().find(|p| &p.name == root_crate)
+    else {
+        return Ok(None);
+    };
+    let metadata = MetadataCommand::new().manifest_path(&package.path).no_deps().exec()?;
+    Ok(metadata
+        .packages
+        .iter()
+        .find(|p| p.name.as_str() == root_crate)
+        .map(|p| p.version.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, path::PathBuf};
+
+    use super::Update;
+    use crate::{
+        config::{PackageId, VersionedPackage},
+        publish_report::PackageKind,
+    };
+
+    fn package(
+        kind: PackageKind,
+        name: &str,
+        dir: &str,
+        dependencies: &[&str],
+    ) -> VersionedPackage {
+        VersionedPackage {
+            kind,
+            name: name.to_string(),
+            version: "0.1.0".to_string(),
+            dir: PathBuf::from(dir),
+            path: PathBuf::from(dir).join("Cargo.toml"),
+            dependencies: dependencies.iter().map(ToString::to_string).collect(),
+            workspace_version: None,
+        }
+    }
+
+    #[test]
+    fn crate_and_npm_package_may_share_a_name() {
+        let packages = [
+            package(PackageKind::Crate, "oxlint", "apps/oxlint", &[]),
+            package(PackageKind::Crate, "oxc_linter", "crates/oxc_linter", &["oxlint"]),
+            package(PackageKind::Npm, "oxlint", "npm/oxlint", &[]),
+            package(PackageKind::Npm, "oxlint-plugins", "npm/oxlint-plugins", &["oxlint"]),
+        ];
+        let mut versions =
+            BTreeMap::from([(PackageId::new(PackageKind::Npm, "oxlint"), "0.2.0".to_string())]);
+        Update::bump_dependents(&packages, &mut versions, |_| Ok("0.1.1".to_string())).unwrap();
+        assert_eq!(
+            versions,
+            BTreeMap::from([
+                (PackageId::new(PackageKind::Npm, "oxlint"), "0.2.0".to_string()),
+                (PackageId::new(PackageKind::Npm, "oxlint-plugins"), "0.1.1".to_string()),
+            ])
+        );
+    }
+}
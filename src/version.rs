@@ -0,0 +1,93 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{Context, Result};
+use cargo_metadata::semver::{Prerelease, Version};
+
+/// A semver increment requested with `--bump`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Bump {
+    Patch,
+    Minor,
+    Major,
+}
+
+impl Bump {
+    /// Increment `version`. A pre-release is promoted to its own version when that already
+    /// satisfies the increment, e.g. a minor bump of `1.3.0-beta.1` is `1.3.0`.
+    #[must_use]
+    pub fn apply(self, version: &Version) -> Version {
+        let Version { major, minor, patch, .. } = *version;
+        let pre = !version.pre.is_empty();
+        match self {
+            Self::Major if pre && minor == 0 && patch == 0 => Version::new(major, 0, 0),
+            Self::Major => Version::new(major + 1, 0, 0),
+            Self::Minor if pre && patch == 0 => Version::new(major, minor, 0),
+            Self::Minor => Version::new(major, minor + 1, 0),
+            Self::Patch if pre => Version::new(major, minor, patch),
+            Self::Patch => Version::new(major, minor, patch + 1),
+        }
+    }
+}
+
+impl FromStr for Bump {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "major" => Ok(Self::Major),
+            "minor" => Ok(Self::Minor),
+            "patch" => Ok(Self::Patch),
+            _ => Err(format!("expected `major`, `minor` or `patch`, got `{s}`")),
+        }
+    }
+}
+
+impl fmt::Display for Bump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Major => "major",
+            Self::Minor => "minor",
+            Self::Patch => "patch",
+        })
+    }
+}
+
+/// `next` as a `<channel>.<n>` pre-release. The counter continues when `current` is
+/// already a pre-release of the same version and channel, and starts at 0 otherwise.
+pub fn pre_release_version(current: &Version, mut next: Version, channel: &str) -> Result<Version> {
+    anyhow::ensure!(
+        !channel.is_empty() && !channel.contains('.'),
+        "pre-release channel `{channel}` must be a single identifier such as `beta`"
+    );
+    // Without releasable commits the stable bump stays put; a pre-release of the
+    // current version would sort below it.
+    if current.pre.is_empty() && next.cmp_precedence(current).is_le() {
+        next.patch = current.patch + 1;
+    }
+    let counter = match current.pre.as_str().split_once('.') {
+        Some((current_channel, n))
+            if current_channel == channel && stable(current) == stable(&next) =>
+        {
+            n.parse::<u64>().with_context(|| format!("cannot continue pre-release {current}"))? + 1
+        }
+        _ => 0,
+    };
+    next.pre = Prerelease::new(&format!("{channel}.{counter}"))?;
+    anyhow::ensure!(
+        next.cmp_precedence(current).is_gt(),
+        "pre-release {next} would not be greater than the current version {current}"
+    );
+    Ok(next)
+}
+
+/// `version` without pre-release and build metadata.
+pub const fn stable(version: &Version) -> Version {
+    Version::new(version.major, version.minor, version.patch)
+}
+
+/// The channel of a pre-release version, e.g. `beta` for `1.3.0-beta.1`.
+pub fn pre_release_channel(version: &str) -> Option<String> {
+    let version = Version::parse(version).ok()?;
+    let pre = version.pre.as_str();
+    (!pre.is_empty()).then(|| pre.split('.').next().unwrap_or(pre).to_string())
+}
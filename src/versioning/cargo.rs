@@ -1,80 +1,217 @@
 use std::{
+    collections::BTreeMap,
     fs,
     path::{Path, PathBuf},
     str::FromStr,
 };
 
 use anyhow::{Context, Result};
-use cargo_metadata::MetadataCommand;
-use toml_edit::{DocumentMut, Formatted, Value};
+use cargo_metadata::{DependencyKind, MetadataCommand, Package};
+use toml_edit::{DocumentMut, InlineTable, Item, TableLike, Value};
 
-use crate::config::VersionedPackage;
+use crate::{
+    changes::Changes,
+    config::{PackageId, VersionedPackage},
+    publish_report::PackageKind,
+};
 
 #[derive(Debug)]
 pub struct CargoToml {
-    path: PathBuf,
-
     is_workspace: bool,
 
+    /// Add released crates missing from `[workspace.dependencies]`.
+    add_workspace_dependencies: bool,
+
     packages: Vec<VersionedPackage>,
+
+    /// Manifests whose path dependencies on released packages are bumped: every workspace
+    /// member, including unpublished ones, and the workspace root.
+    manifests: Vec<PathBuf>,
+
+    /// `Cargo.lock` of the workspace.
+    lockfile: PathBuf,
+
+    /// Root manifest of the workspace, whose `[workspace.dependencies]` are bumped.
+    workspace_manifest: PathBuf,
 }
 
+/// Dependency tables of a manifest, also nested in `[target.*]`.
+const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "build-dependencies", "dev-dependencies"];
+
 impl CargoToml {
-    pub fn new(path: &Path) -> Result<Self> {
+    pub fn new(path: &Path, add_workspace_dependencies: bool) -> Result<Self> {
         let dir = path.parent().unwrap();
 
         let toml = DocumentMut::from_str(&fs::read_to_string(path)?)?;
         let is_workspace = toml.contains_key("workspace");
 
-        let packages = if is_workspace {
+        let (metadata, packages) = if is_workspace {
             let metadata = MetadataCommand::new().current_dir(dir).no_deps().exec()?;
-            metadata
+            let packages = metadata
                 .workspace_packages()
                 .into_iter()
                 // `publish.is_none()` means `publish = true`.
                 .filter(|p| p.publish.is_none())
-                .map(|p| VersionedPackage {
-                    name: p.name.to_string(),
-                    dir: p.manifest_path.parent().unwrap().as_std_path().to_path_buf(),
-                    path: p.manifest_path.as_std_path().to_path_buf(),
-                })
-                .collect::<Vec<_>>()
+                .map(|p| versioned_package(p, path))
+                .collect::<Result<Vec<_>>>()?;
+            (metadata, packages)
         } else {
-            vec![VersionedPackage {
-                name: toml
-                    .get("package")
-                    .and_then(|item| item.as_table())
-                    .and_then(|table| table.get("name"))
-                    .and_then(|value| value.as_str())
-                    .context("expect package name")?
-                    .to_string(),
-                dir: path.parent().unwrap().to_path_buf(),
-                path: path.to_path_buf(),
-            }]
+            let name = toml
+                .get("package")
+                .and_then(|item| item.as_table())
+                .and_then(|table| table.get("name"))
+                .and_then(|value| value.as_str())
+                .context("expect package name")?;
+            let metadata = MetadataCommand::new().manifest_path(path).no_deps().exec()?;
+            let package = metadata
+                .packages
+                .iter()
+                .find(|p| p.name.as_str() == name)
+                .with_context(|| format!("package `{name}` not found: {}", path.display()))?;
+            let workspace_manifest = metadata.workspace_root.join("Cargo.toml");
+            let package = versioned_package(package, workspace_manifest.as_std_path())?;
+            // Bumping `[workspace.package]` would re-version every other inheriting member.
+            anyhow::ensure!(
+                package.workspace_version.is_none(),
+                "`{name}` inherits `version.workspace = true`, add the workspace manifest \
+                 {workspace_manifest} to `versioned_files` instead of {}",
+                path.display()
+            );
+            (metadata, vec![package])
         };
+        let workspace_manifest = metadata.workspace_root.join("Cargo.toml").into_std_path_buf();
+        let mut manifests = metadata
+            .workspace_packages()
+            .iter()
+            .map(|p| p.manifest_path.as_std_path().to_path_buf())
+            .collect::<Vec<_>>();
+        if !manifests.contains(&workspace_manifest) {
+            manifests.push(workspace_manifest.clone());
+        }
 
-        Ok(Self { path: path.to_path_buf(), is_workspace, packages })
+        Ok(Self {
+            is_workspace,
+            add_workspace_dependencies,
+            packages,
+            manifests,
+            lockfile: metadata.workspace_root.join("Cargo.lock").into_std_path_buf(),
+            workspace_manifest,
+        })
     }
 
     pub fn packages(&self) -> Vec<VersionedPackage> {
         self.packages.clone()
     }
 
-    pub fn update_version(&self, version: &str) -> Result<()> {
-        if self.is_workspace {
-            let mut workspace_toml = CargoTomlFile::new(&self.path)?;
-            for package in &self.packages {
-                workspace_toml.set_workspace_dependency_version(&package.name, version)?;
+    /// Bump the packages in `versions`.
+    pub fn update_versions(
+        &self,
+        versions: &BTreeMap<PackageId, String>,
+        changes: &mut Changes,
+    ) -> Result<()> {
+        let packages = self
+            .packages
+            .iter()
+            .filter_map(|package| Some((package, versions.get(&package.id())?)))
+            .collect::<Vec<_>>();
+        let mut workspace_toml = CargoTomlFile::new(&self.workspace_manifest, changes)?;
+        for (package, version) in &packages {
+            // A single crate only has its existing entry bumped.
+            if self.is_workspace || workspace_toml.has_workspace_dependency(&package.name) {
+                workspace_toml.set_workspace_dependency_version(
+                    package,
+                    version,
+                    self.add_workspace_dependencies,
+                )?;
             }
-            workspace_toml.save()?;
         }
-        for package in &self.packages {
-            let mut package_toml = CargoTomlFile::new(&package.path)?;
+        workspace_toml.save(changes);
+        // Packages inheriting the version share it, so `[workspace.package]` is bumped once.
+        let workspace_versions = packages
+            .iter()
+            .filter_map(|(package, version)| Some((package.workspace_version.as_ref()?, version)))
+            .collect::<BTreeMap<_, _>>();
+        for (manifest, version) in workspace_versions {
+            let mut workspace_toml = CargoTomlFile::new(manifest, changes)?;
+            workspace_toml.set_workspace_package_version(version)?;
+            workspace_toml.save(changes);
+        }
+        for manifest in &self.manifests {
+            let mut toml = CargoTomlFile::new(manifest, changes)?;
+            toml.set_dependency_versions(versions);
+            toml.save(changes);
+        }
+        for (package, version) in packages {
+            if package.workspace_version.is_some() {
+                continue;
+            }
+            let mut package_toml = CargoTomlFile::new(&package.path, changes)?;
             package_toml.set_package_version(version)?;
-            package_toml.save()?;
+            package_toml.save(changes);
+        }
+        if self.lockfile.exists() {
+            let versions = self.member_versions(changes)?;
+            let mut lockfile = CargoTomlFile::new(&self.lockfile, changes)?;
+            lockfile.set_lockfile_versions(&versions);
+            lockfile.save(changes);
         }
         Ok(())
     }
+
+    /// The version of every workspace member after the manifest edits in `changes`, keyed by
+    /// name. Unpublished members inheriting `[workspace.package]` change version too.
+    fn member_versions(&self, changes: &Changes) -> Result<BTreeMap<String, String>> {
+        let workspace_toml = CargoTomlFile::new(&self.workspace_manifest, changes)?;
+        let workspace_version = workspace_toml
+            .toml
+            .get("workspace")
+            .and_then(|workspace| workspace.get("package"))
+            .and_then(|package| package.get("version"))
+            .and_then(Item::as_str)
+            .map(str::to_string);
+        let mut versions = BTreeMap::new();
+        for manifest in &self.manifests {
+            let toml = CargoTomlFile::new(manifest, changes)?;
+            let Some(package) = toml.toml.get("package") else { continue };
+            let Some(name) = package.get("name").and_then(Item::as_str) else { continue };
+            let Some(version) = package.get("version") else { continue };
+            let version = if version.get("workspace").and_then(Item::as_bool) == Some(true) {
+                workspace_version.clone()
+            } else {
+                version.as_str().map(str::to_string)
+            };
+            if let Some(version) = version {
+                versions.insert(name.to_string(), version);
+            }
+        }
+        Ok(versions)
+    }
+}
+
+/// `workspace_manifest` is the root manifest of the package's workspace.
+fn versioned_package(package: &Package, workspace_manifest: &Path) -> Result<VersionedPackage> {
+    let manifest = DocumentMut::from_str(&fs::read_to_string(&package.manifest_path)?)?;
+    let inherits_version = manifest
+        .get("package")
+        .and_then(|item| item.get("version"))
+        .and_then(|item| item.get("workspace"))
+        .and_then(Item::as_bool)
+        .unwrap_or(false);
+    let dependencies = package
+        .dependencies
+        .iter()
+        .filter(|dependency| dependency.kind != DependencyKind::Development)
+        .map(|dependency| dependency.name.clone())
+        .collect();
+    Ok(VersionedPackage {
+        kind: PackageKind::Crate,
+        name: package.name.to_string(),
+        version: package.version.to_string(),
+        dir: package.manifest_path.parent().unwrap().as_std_path().to_path_buf(),
+        path: package.manifest_path.as_std_path().to_path_buf(),
+        dependencies,
+        workspace_version: inherits_version.then(|| workspace_manifest.to_path_buf()),
+    })
 }
 
 struct CargoTomlFile {
@@ -83,35 +220,158 @@ struct CargoTomlFile {
 }
 
 impl CargoTomlFile {
-    fn new(path: &Path) -> Result<Self> {
-        let toml = DocumentMut::from_str(&fs::read_to_string(path)?)?;
+    fn new(path: &Path, changes: &Changes) -> Result<Self> {
+        let toml = DocumentMut::from_str(&changes.read(path)?)?;
         Ok(Self { path: path.to_path_buf(), toml })
     }
 
-    fn save(self) -> Result<()> {
-        let serialized = self.toml.to_string();
-        fs::write(self.path, serialized)?;
+    fn save(self, changes: &mut Changes) {
+        changes.write(&self.path, self.toml.to_string());
+    }
+
+    fn has_workspace_dependency(&self, name: &str) -> bool {
+        self.toml
+            .get("workspace")
+            .and_then(|workspace| workspace.get("dependencies"))
+            .and_then(|dependencies| dependencies.get(name))
+            .is_some()
+    }
+
+    /// Set the version of `package` in `[workspace.dependencies]`, which may be a bare version
+    /// string, an inline table, or a standard or dotted table. A missing entry is added with
+    /// `add_missing`, and otherwise skipped with a warning.
+    fn set_workspace_dependency_version(
+        &mut self,
+        package: &VersionedPackage,
+        version: &str,
+        add_missing: bool,
+    ) -> Result<()> {
+        let Some(workspace) = self.toml.get_mut("workspace").and_then(Item::as_table_like_mut)
+        else {
+            anyhow::bail!("`workspace` field not found: {}", self.path.display());
+        };
+        if add_missing && !workspace.contains_key("dependencies") {
+            workspace.insert("dependencies", toml_edit::table());
+        }
+        let Some(dependencies) =
+            workspace.get_mut("dependencies").and_then(Item::as_table_like_mut)
+        else {
+            eprintln!(
+                "warning: `{}` is missing from `[workspace.dependencies]`: {}",
+                package.name,
+                self.path.display()
+            );
+            return Ok(());
+        };
+        let Some(entry) = dependencies.get_mut(&package.name) else {
+            if !add_missing {
+                eprintln!(
+                    "warning: `{}` is missing from `[workspace.dependencies]`: {}",
+                    package.name,
+                    self.path.display()
+                );
+                return Ok(());
+            }
+            let dir = self.path.parent().unwrap();
+            let path = package.dir.strip_prefix(dir).unwrap_or(&package.dir);
+            let mut entry = InlineTable::new();
+            entry.insert("path", Value::from(path.to_string_lossy().replace('\\', "/")));
+            entry.insert("version", Value::from(version));
+            dependencies.insert(&package.name, Item::Value(Value::InlineTable(entry)));
+            return Ok(());
+        };
+        if let Some(value) = entry.as_value_mut().filter(|value| value.is_str()) {
+            set_requirement(value, version);
+            return Ok(());
+        }
+        let Some(table) = entry.as_table_like_mut() else {
+            anyhow::bail!(
+                "unsupported `workspace.dependencies.{}` entry: {}",
+                package.name,
+                self.path.display()
+            );
+        };
+        match table.get_mut("version").and_then(Item::as_value_mut) {
+            Some(value) => set_requirement(value, version),
+            None => {
+                table.insert("version", toml_edit::value(version));
+            }
+        }
         Ok(())
     }
 
-    fn set_workspace_dependency_version(&mut self, crate_name: &str, version: &str) -> Result<()> {
-        let Some(table) = self
-            .toml
-            .get_mut("workspace")
-            .and_then(|item| item.as_table_mut())
-            .and_then(|table| table.get_mut("dependencies"))
-            .and_then(|item| item.as_table_mut())
+    /// Set the version requirement of every path dependency on a package in `versions`,
+    /// keeping its `=`, `^` or `~` operator.
+    fn set_dependency_versions(&mut self, versions: &BTreeMap<PackageId, String>) {
+        for kind in DEPENDENCY_TABLES {
+            if let Some(table) = self.toml.get_mut(kind).and_then(Item::as_table_like_mut) {
+                set_path_dependency_versions(table, versions);
+            }
+        }
+        let Some(targets) = self.toml.get_mut("target").and_then(Item::as_table_like_mut) else {
+            return;
+        };
+        for (_, target) in targets.iter_mut() {
+            for kind in DEPENDENCY_TABLES {
+                if let Some(table) = target.get_mut(kind).and_then(Item::as_table_like_mut) {
+                    set_path_dependency_versions(table, versions);
+                }
+            }
+        }
+    }
+
+    /// Set the version of the workspace members in `versions` in a `Cargo.lock`, and in the
+    /// dependency lists naming their version.
+    fn set_lockfile_versions(&mut self, versions: &BTreeMap<String, String>) {
+        let Some(entries) = self.toml.get_mut("package").and_then(Item::as_array_of_tables_mut)
         else {
-            anyhow::bail!("`workspace.dependencies` field not found: {}", self.path.display());
+            return;
         };
-        let Some(version_field) = table
-            .get_mut(crate_name)
-            .and_then(|item| item.as_inline_table_mut())
+        // `"name old_version"` to `"name new_version"`.
+        let mut bumped = BTreeMap::new();
+        for entry in entries.iter_mut() {
+            // Registry and git packages have a `source`, workspace members do not.
+            if entry.contains_key("source") {
+                continue;
+            }
+            let Some(name) = entry.get("name").and_then(Item::as_str).map(str::to_string) else {
+                continue;
+            };
+            let Some(version) = versions.get(&name) else { continue };
+            let Some(value) = entry.get_mut("version").and_then(Item::as_value_mut) else {
+                continue;
+            };
+            let old_version = value.as_str().unwrap_or_default();
+            if old_version == version {
+                continue;
+            }
+            bumped.insert(format!("{name} {old_version}"), format!("{name} {version}"));
+            set_version(value, version);
+        }
+        for entry in entries.iter_mut() {
+            let Some(dependencies) = entry.get_mut("dependencies").and_then(Item::as_array_mut)
+            else {
+                continue;
+            };
+            for dependency in dependencies.iter_mut() {
+                if let Some(new) = dependency.as_str().and_then(|d| bumped.get(d)) {
+                    set_version(dependency, new);
+                }
+            }
+        }
+    }
+
+    fn set_workspace_package_version(&mut self, version: &str) -> Result<()> {
+        let Some(version_field) = self
+            .toml
+            .get_mut("workspace")
+            .and_then(|item| item.get_mut("package"))
             .and_then(|item| item.get_mut("version"))
+            .and_then(|item| item.as_value_mut())
         else {
-            anyhow::bail!("dependency `{}` not found: {}", crate_name, self.path.display());
+            anyhow::bail!("No `workspace.package.version` field found: {}", self.path.display());
         };
-        *version_field = Value::String(Formatted::new(version.to_string()));
+        set_version(version_field, version);
         Ok(())
     }
 
@@ -125,7 +385,47 @@ impl CargoTomlFile {
         else {
             anyhow::bail!("No `package.version` field found: {}", self.path.display());
         };
-        *version_field = Value::String(Formatted::new(version.to_string()));
+        set_version(version_field, version);
         Ok(())
     }
 }
+
+/// Replace the string `value` with `version`, keeping the whitespace and comments around it.
+fn set_version(value: &mut Value, version: &str) {
+    let decor = value.decor().clone();
+    *value = Value::from(version);
+    *value.decor_mut() = decor;
+}
+
+/// Bump the `version` of the path dependencies in `dependencies` on a package in `versions`.
+/// Renamed dependencies are matched by their `package` field.
+fn set_path_dependency_versions(
+    dependencies: &mut dyn TableLike,
+    versions: &BTreeMap<PackageId, String>,
+) {
+    for (key, item) in dependencies.iter_mut() {
+        let Some(dependency) = item.as_table_like_mut() else { continue };
+        if !dependency.contains_key("path") {
+            continue;
+        }
+        let name = dependency.get("package").and_then(Item::as_str).unwrap_or_else(|| key.get());
+        let Some(version) = versions.get(&PackageId::new(PackageKind::Crate, name)) else {
+            continue;
+        };
+        let version = version.clone();
+        let Some(value) = dependency.get_mut("version").and_then(Item::as_value_mut) else {
+            continue;
+        };
+        set_requirement(value, &version);
+    }
+}
+
+/// Replace the version requirement `value` with `version`, keeping its `=`, `^` or `~`
+/// operator so pinned requirements stay pinned.
+fn set_requirement(value: &mut Value, version: &str) {
+    let operator: String = value
+        .as_str()
+        .map(|requirement| requirement.chars().take_while(|c| "=^~".contains(*c)).collect())
+        .unwrap_or_default();
+    set_version(value, &format!("{operator}{version}"));
+}
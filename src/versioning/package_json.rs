@@ -1,5 +1,4 @@
 use std::{
-    cell::RefCell,
     fs,
     path::{Path, PathBuf},
 };
@@ -7,7 +6,7 @@ use std::{
 use anyhow::{Context, Result};
 use serde_json::Value;
 
-use crate::config::VersionedPackage;
+use crate::{changes::Changes, config::VersionedPackage, publish_report::PackageKind};
 
 type RawJson = serde_json::Map<String, Value>;
 
@@ -15,7 +14,7 @@ type RawJson = serde_json::Map<String, Value>;
 pub struct PackageJson {
     path: PathBuf,
 
-    raw: RefCell<RawJson>,
+    raw: RawJson,
 }
 
 impl PackageJson {
@@ -24,22 +23,193 @@ impl PackageJson {
             .with_context(|| format!("failed to read {}", path.display()))?;
         let raw: RawJson = serde_json::from_str(&content)
             .with_context(|| format!("failed to parse {}", path.display()))?;
-        Ok(Self { path: path.to_path_buf(), raw: RefCell::new(raw) })
+        Ok(Self { path: path.to_path_buf(), raw })
     }
 
     pub fn packages(&self) -> Vec<VersionedPackage> {
         vec![VersionedPackage {
-            name: self.raw.borrow().get("name").unwrap().as_str().unwrap().to_string(),
-            dir: self.path.parent().unwrap().to_path_buf(),
+            kind: PackageKind::Npm,
+            name: self.name().to_string(),
+            version: self.version().unwrap_or("0.0.0").to_string(),
+            dir: self.dir().to_path_buf(),
             path: self.path.clone(),
+            dependencies: self
+                .raw
+                .get("dependencies")
+                .and_then(Value::as_object)
+                .map(|dependencies| dependencies.keys().cloned().collect())
+                .unwrap_or_default(),
+            workspace_version: None,
         }]
     }
 
-    pub fn update_version(&self, version: &str) -> Result<()> {
-        self.raw.borrow_mut().insert("version".to_string(), Value::String(version.to_string()));
-        let mut json = serde_json::to_string_pretty(&self.raw).context("failed to write json")?;
-        json.push('\n');
-        fs::write(&self.path, json).context("failed to write json")?;
+    pub fn name(&self) -> &str {
+        self.raw.get("name").unwrap().as_str().unwrap()
+    }
+
+    pub fn version(&self) -> Option<&str> {
+        self.raw.get("version").and_then(Value::as_str)
+    }
+
+    pub fn dir(&self) -> &Path {
+        self.path.parent().unwrap()
+    }
+
+    /// `"private": true` packages are never published.
+    pub fn is_private(&self) -> bool {
+        self.raw.get("private").and_then(Value::as_bool).unwrap_or(false)
+    }
+
+    /// Replace only the text of the `version` value, or insert it after `name` if missing,
+    /// so indentation, line endings and key order are kept.
+    pub fn update_version(&self, version: &str, changes: &mut Changes) -> Result<()> {
+        let content = changes.read(&self.path)?;
+        let content = set_version(&content, version)
+            .with_context(|| format!("failed to update the version of {}", self.path.display()))?;
+        changes.write(&self.path, content);
         Ok(())
     }
 }
+
+/// `json` with the top-level `version` set to `version`.
+fn set_version(json: &str, version: &str) -> Result<String> {
+    if let Some((_, value)) = find_field(json, "version") {
+        let end = scalar_end(json.as_bytes(), value).context("`version` is not a scalar")?;
+        return Ok(format!("{}\"{version}\"{}", &json[..value], &json[end..]));
+    }
+    let (key_start, value) = find_field(json, "name").context("no `name` field found")?;
+    anyhow::ensure!(json[value..].starts_with('"'), "`name` is not a string");
+    let name_end = scalar_end(json.as_bytes(), value).context("unterminated `name`")?;
+    let line_start = json[..key_start].rfind('\n').map_or(0, |i| i + 1);
+    let indent = &json[line_start..key_start];
+    let separator = if indent.trim().is_empty() {
+        let newline = if json.contains("\r\n") { "\r\n" } else { "\n" };
+        format!("{newline}{indent}")
+    } else {
+        " ".to_string()
+    };
+    let (before, after) = json.split_at(name_end);
+    Ok(format!("{before},{separator}\"version\": \"{version}\"{after}"))
+}
+
+/// Offsets of the top-level `key` of a JSON object and of its value.
+fn find_field(json: &str, key: &str) -> Option<(usize, usize)> {
+    let bytes = json.as_bytes();
+    let mut depth = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'{' | b'[' => depth += 1,
+            b'}' | b']' => depth -= 1,
+            b'"' => {
+                let start = i;
+                i = string_end(bytes, start)?;
+                // Only keys are followed by `:`.
+                let value = json[i + 1..].trim_start().strip_prefix(':').map(str::trim_start);
+                if depth == 1
+                    && &json[start + 1..i] == key
+                    && let Some(value) = value
+                {
+                    return Some((start, json.len() - value.len()));
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// End offset of the string, number, boolean or `null` starting at `start`,
+/// `None` for objects and arrays.
+fn scalar_end(bytes: &[u8], start: usize) -> Option<usize> {
+    match bytes.get(start)? {
+        b'"' => string_end(bytes, start).map(|end| end + 1),
+        b'{' | b'[' => None,
+        _ => Some(
+            bytes[start..]
+                .iter()
+                .position(|b| matches!(b, b',' | b'}' | b']') || b.is_ascii_whitespace())
+                .map_or(bytes.len(), |len| start + len),
+        ),
+    }
+}
+
+/// Offset of the closing quote of the JSON string starting at `start`.
+const fn string_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return Some(i),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::set_version;
+
+    #[test]
+    fn replaces_version() {
+        let json =
+            "{\n  \"name\": \"oxlint\",\n  \"version\": \"0.1.0\",\n  \"private\": false\n}\n";
+        let expected =
+            "{\n  \"name\": \"oxlint\",\n  \"version\": \"0.2.0\",\n  \"private\": false\n}\n";
+        assert_eq!(set_version(json, "0.2.0").unwrap(), expected);
+    }
+
+    #[test]
+    fn skips_escaped_quotes() {
+        let json = r#"{"description": "a \"version\": \"9.9.9\" string", "version": "0.1.0"}"#;
+        let expected = r#"{"description": "a \"version\": \"9.9.9\" string", "version": "0.2.0"}"#;
+        assert_eq!(set_version(json, "0.2.0").unwrap(), expected);
+    }
+
+    #[test]
+    fn skips_nested_version() {
+        let json = r#"{"engines": {"version": "1.0.0"}, "name": "oxlint", "version": "0.1.0"}"#;
+        let expected = r#"{"engines": {"version": "1.0.0"}, "name": "oxlint", "version": "0.2.0"}"#;
+        assert_eq!(set_version(json, "0.2.0").unwrap(), expected);
+
+        let json = r#"{"name": "oxlint", "engines": {"version": "1.0.0"}}"#;
+        let expected = r#"{"name": "oxlint", "version": "0.2.0", "engines": {"version": "1.0.0"}}"#;
+        assert_eq!(set_version(json, "0.2.0").unwrap(), expected);
+    }
+
+    #[test]
+    fn inserts_with_tabs() {
+        let json = "{\n\t\"name\": \"oxlint\"\n}\n";
+        let expected = "{\n\t\"name\": \"oxlint\",\n\t\"version\": \"0.2.0\"\n}\n";
+        assert_eq!(set_version(json, "0.2.0").unwrap(), expected);
+    }
+
+    #[test]
+    fn inserts_with_crlf() {
+        let json = "{\r\n  \"name\": \"oxlint\",\r\n  \"private\": false\r\n}\r\n";
+        let expected = "{\r\n  \"name\": \"oxlint\",\r\n  \"version\": \"0.2.0\",\r\n  \"private\": false\r\n}\r\n";
+        assert_eq!(set_version(json, "0.2.0").unwrap(), expected);
+    }
+
+    #[test]
+    fn keeps_missing_trailing_newline() {
+        let json = "{\n  \"name\": \"oxlint\",\n  \"version\": \"0.1.0\"\n}";
+        let expected = "{\n  \"name\": \"oxlint\",\n  \"version\": \"0.2.0\"\n}";
+        assert_eq!(set_version(json, "0.2.0").unwrap(), expected);
+    }
+
+    #[test]
+    fn replaces_non_string_version() {
+        let json = "{\n  \"name\": \"oxlint\",\n  \"version\": null\n}\n";
+        let expected = "{\n  \"name\": \"oxlint\",\n  \"version\": \"0.2.0\"\n}\n";
+        assert_eq!(set_version(json, "0.2.0").unwrap(), expected);
+
+        let json = r#"{"name": "oxlint", "version": 1, "private": false}"#;
+        let expected = r#"{"name": "oxlint", "version": "0.2.0", "private": false}"#;
+        assert_eq!(set_version(json, "0.2.0").unwrap(), expected);
+
+        assert!(set_version(r#"{"name": "oxlint", "version": {}}"#, "0.2.0").is_err());
+    }
+}